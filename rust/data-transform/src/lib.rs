@@ -3,355 +3,8266 @@
 //! Compiled into the WASM binary alongside the kernel.
 //! Processes structured data at near-native speed.
 
+use std::cell::RefCell;
+
+use regex::Regex;
 use serde_json::{json, Value};
 
+/// Record-by-record operations that honor a `where` clause: only matching
+/// records are transformed and the rest pass through unchanged.
+const WHERE_SCOPED_OPERATIONS: &[&str] = &[
+    "format_numbers",
+    "extract",
+    "score",
+    "checksum",
+    "map",
+    "normalize",
+    "fillna",
+    "zscore",
+];
+
+/// Signature shared by every operation: the full input object in, the
+/// result (or an error) out.
+pub type Handler = fn(&Value) -> Result<Value, TransformError>;
+
+/// Why an operation failed. `Display` renders the message alone, so callers
+/// that only need text can keep using `to_string()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransformError {
+    /// A required parameter (or `data`) is absent.
+    MissingField(String),
+    /// A parameter is present but has the wrong JSON type.
+    WrongType(String),
+    /// The operation doesn't exist or isn't enabled.
+    UnknownOperation(String),
+    /// A parameter is well-formed but its value is rejected.
+    InvalidValue(String),
+}
+
+impl TransformError {
+    /// The same kind of error with `context` prefixed to its message.
+    fn with_context(self, context: &str) -> Self {
+        let prefix = |m: String| format!("{context}: {m}");
+        match self {
+            Self::MissingField(m) => Self::MissingField(prefix(m)),
+            Self::WrongType(m) => Self::WrongType(prefix(m)),
+            Self::UnknownOperation(m) => Self::UnknownOperation(prefix(m)),
+            Self::InvalidValue(m) => Self::InvalidValue(prefix(m)),
+        }
+    }
+
+    /// The human-readable message, without the error kind.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::MissingField(m)
+            | Self::WrongType(m)
+            | Self::UnknownOperation(m)
+            | Self::InvalidValue(m) => m,
+        }
+    }
+}
+
+impl std::fmt::Display for TransformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for TransformError {}
+
+/// Messages from helpers that don't classify their errors.
+impl From<String> for TransformError {
+    fn from(message: String) -> Self {
+        Self::InvalidValue(message)
+    }
+}
+
+impl From<&str> for TransformError {
+    fn from(message: &str) -> Self {
+        Self::InvalidValue(message.to_string())
+    }
+}
+
+/// Built-in operations, in documentation order.
+const BUILTINS: &[(&str, Handler)] = &[
+    ("stats", compute_stats),
+    ("filter", filter_data),
+    ("sort", sort_data),
+    ("validate", validate_schema),
+    ("aggregate", aggregate_data),
+    ("nearest", nearest_records),
+    ("merge_objects", merge_objects),
+    ("growth_rate", growth_rate),
+    ("format_numbers", format_numbers),
+    ("find_duplicates", find_duplicates),
+    ("linreg", linear_regression),
+    ("value_counts", value_counts),
+    ("slice", slice_data),
+    ("zip", zip_columns),
+    ("infer_schema", infer_schema),
+    ("omit", omit_fields),
+    ("group_normalize", group_normalize),
+    ("rle", run_length_encode),
+    ("extract", extract_pattern),
+    ("histogram", histogram),
+    ("score", composite_score),
+    ("cond_aggregate", conditional_aggregate),
+    ("interpolate", interpolate),
+    ("detect_jumps", detect_jumps),
+    ("weighted_sample", weighted_sample),
+    ("sparkline", sparkline),
+    ("crosstab", crosstab),
+    ("checksum", checksum),
+    ("unpivot", unpivot),
+    ("csv_parse", csv_parse),
+    ("csv_format", csv_format),
+    ("gini", gini),
+    ("partition", partition),
+    ("object_diff", object_diff),
+    ("zscore", zscore),
+    ("group_by", group_by),
+    ("distinct", distinct),
+    ("select", select_fields),
+    ("rename", rename_fields),
+    ("count", count_rows),
+    ("correlate", correlate),
+    ("moving_average", moving_average),
+    ("cumsum", cumulative_sum),
+    ("normalize", normalize_field),
+    ("outliers", outliers),
+    ("top_n", top_n),
+    ("join", join_records),
+    ("pivot", pivot),
+    ("flatten", flatten_records),
+    ("fillna", fill_missing),
+    ("map", map_fields),
+    ("pipeline", pipeline),
+];
+
+/// Operation names mapped to handlers, in registration order.
+struct Registry {
+    operations: Vec<(String, Handler)>,
+}
+
+impl Registry {
+    fn builtin() -> Self {
+        Self {
+            operations: BUILTINS
+                .iter()
+                .map(|(name, handler)| (name.to_string(), *handler))
+                .collect(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Handler> {
+        self.operations
+            .iter()
+            .find(|(registered, _)| registered == name)
+            .map(|(_, handler)| *handler)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.operations
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+thread_local! {
+    /// Every operation `execute` can dispatch to; see [`register_operation`].
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::builtin());
+
+    /// Allowlist set by [`set_enabled_operations`]; `None` enables everything.
+    static ENABLED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Add an operation that `execute` will dispatch to by `name`. It is listed
+/// by [`supported_operations`] and subject to the allowlist like the
+/// built-ins. Names already registered are rejected.
+pub fn register_operation(name: &str, handler: Handler) -> Result<(), String> {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        if registry.get(name).is_some() {
+            return Err(format!("Operation '{name}' is already registered"));
+        }
+        registry.operations.push((name.to_string(), handler));
+        Ok(())
+    })
+}
+
+/// Restrict `execute` to the named operations, or re-enable all of them with
+/// `None`. Unknown names are rejected so typos don't silently disable an
+/// operation.
+pub fn set_enabled_operations(operations: Option<Vec<String>>) -> Result<(), String> {
+    REGISTRY.with(|registry| {
+        let registry = registry.borrow();
+        match operations
+            .iter()
+            .flatten()
+            .find(|op| registry.get(op).is_none())
+        {
+            Some(unknown) => Err(format!(
+                "Unknown operation: {unknown}. Use: {}",
+                registry.names().join(", ")
+            )),
+            None => Ok(()),
+        }
+    })?;
+    ENABLED.with(|enabled| *enabled.borrow_mut() = operations);
+    Ok(())
+}
+
+/// Operations currently enabled for `execute`: the built-ins in
+/// documentation order, then any registered ones.
+pub fn supported_operations() -> Vec<String> {
+    let enabled = ENABLED.with(|enabled| enabled.borrow().clone());
+    REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .names()
+            .into_iter()
+            .filter(|op| {
+                enabled
+                    .as_ref()
+                    .is_none_or(|list| list.iter().any(|e| e == op))
+            })
+            .map(String::from)
+            .collect()
+    })
+}
+
 /// Execute a data transform operation.
 ///
-/// Operations: stats, filter, sort, validate, aggregate.
-pub fn execute(input: &Value) -> Result<Value, String> {
-    let operation = input
-        .get("operation")
+/// Operations: stats, filter, sort, validate, aggregate, nearest,
+/// merge_objects, growth_rate, format_numbers, find_duplicates, linreg,
+/// value_counts, slice, zip, infer_schema, omit, group_normalize, rle, extract,
+/// histogram, score, cond_aggregate, interpolate, detect_jumps,
+/// weighted_sample, sparkline, crosstab, checksum, unpivot, csv_parse,
+/// csv_format, gini, partition, object_diff, zscore, group_by, distinct,
+/// select, rename, count, correlate, moving_average, cumsum, normalize,
+/// outliers, top_n, join, pivot, flatten, fillna, map, pipeline.
+///
+/// Further operations can be plugged in with [`register_operation`].
+///
+/// Object keys are emitted in a stable order: input records keep their
+/// original key order, and result objects list keys in the order each
+/// operation builds them, so identical calls serialize identically.
+///
+/// format_numbers, extract, score, checksum, map, normalize, fillna and zscore
+/// accept a filter-style `where` clause that limits them to matching records;
+/// the others pass through unchanged and `updated` counts the records
+/// transformed.
+///
+/// Operations outside the [`set_enabled_operations`] allowlist fail with
+/// `operation 'X' is not enabled`.
+///
+/// With `"cache_key": true`, object results also carry a `cache_key`: a
+/// deterministic hash of the canonical input (operation, data and parameters)
+/// that callers can use to memoize identical calls.
+///
+/// A `data` string holding a JSON array or object (a common LLM mistake) is
+/// parsed before dispatch. Any other string is passed through unchanged, so
+/// the operation reports its usual shape error.
+///
+/// With `"format": "csv"`, `data` is parsed from the CSV text in `csv` first,
+/// honoring csv_parse's `delimiter`, `header`, `infer_types` and
+/// `null_values`, so any operation can take CSV input. Likewise
+/// `"output_format": "csv"` turns a result's `data` array into CSV text
+/// (header from every key in first-seen order, RFC 4180 quoting, empty cells
+/// for missing keys).
+///
+/// Failures are [`TransformError`]s, so callers can tell a missing parameter
+/// from a wrong type or an unknown operation; `to_string()` gives the message.
+pub fn execute(input: &Value) -> Result<Value, TransformError> {
+    let parsed = match csv_input(input)? {
+        Some(parsed) => Some(parsed),
+        None => parse_string_data(input),
+    };
+    let input = parsed.as_ref().unwrap_or(input);
+    let csv_output = match input.get("output_format").and_then(|v| v.as_str()) {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(other) => {
+            return Err(format!("Unknown output_format: {other}. Use: json, csv").into());
+        }
+    };
+    let operation =
+        input
+            .get("operation")
+            .and_then(|v| v.as_str())
+            .ok_or(TransformError::MissingField(
+                "Missing 'operation' field".into(),
+            ))?;
+    let registered = REGISTRY.with(|registry| registry.borrow().get(operation).is_some());
+    if registered && !supported_operations().iter().any(|op| op == operation) {
+        return Err(TransformError::UnknownOperation(format!(
+            "operation '{operation}' is not enabled"
+        )));
+    }
+
+    let mut result = match input.get("where") {
+        Some(where_clause) if WHERE_SCOPED_OPERATIONS.contains(&operation) => {
+            dispatch_where(operation, input, where_clause)?
+        }
+        _ => dispatch(operation, input)?,
+    };
+    if csv_output {
+        if let Some(data) = result.get("data").filter(|d| d.is_array()) {
+            let mut csv = json!({"data": data});
+            for option in ["delimiter", "null_output"] {
+                if let Some(value) = input.get(option) {
+                    csv[option] = value.clone();
+                }
+            }
+            result["data"] = csv_format(&csv)?["data"].take();
+        }
+    }
+    if input
+        .get("cache_key")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        if let Some(obj) = result.as_object_mut() {
+            obj.insert("cache_key".into(), json!(cache_key(input)));
+        }
+    }
+    Ok(result)
+}
+
+/// `input[key]` as an array: absent is `MissingField`, any other type is
+/// `WrongType`; both keep the familiar "must be an array" message.
+fn array_field<'a>(input: &'a Value, key: &str) -> Result<&'a Vec<Value>, TransformError> {
+    let message = || format!("'{key}' must be an array");
+    match input.get(key) {
+        None => Err(TransformError::MissingField(message())),
+        Some(value) => value
+            .as_array()
+            .ok_or_else(|| TransformError::WrongType(message())),
+    }
+}
+
+/// Run a record-by-record operation on the records matching `where_clause`
+/// and splice its output back into place, leaving other records untouched.
+/// Statistics an operation derives from the whole input (such as `score`
+/// normalization) are computed over the matching records only.
+fn dispatch_where(
+    operation: &str,
+    input: &Value,
+    where_clause: &Value,
+) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let condition = Predicate::parse(where_clause)?;
+    let matching: Vec<usize> = (0..data.len())
+        .filter(|&i| condition.evaluate(&data[i]).is_match())
+        .collect();
+
+    let mut scoped = input.clone();
+    if let Some(obj) = scoped.as_object_mut() {
+        obj.remove("where");
+    }
+    scoped["data"] = json!(matching.iter().map(|&i| &data[i]).collect::<Vec<_>>());
+    let mut result = dispatch(operation, &scoped)?;
+
+    let mut rows = data.clone();
+    if let Some(updated) = result.get("data").and_then(|v| v.as_array()) {
+        for (&i, record) in matching.iter().zip(updated) {
+            rows[i] = record.clone();
+        }
+    }
+    result["data"] = json!(rows);
+    result["count"] = json!(rows.len());
+    result["updated"] = json!(matching.len());
+    Ok(result)
+}
+
+/// Run `steps` in order, each an operation object without `data`: the first
+/// gets the pipeline's `data` and each later one the `data` array returned
+/// by the step before. Returns the last step's result; `"trace": true` adds
+/// each step's operation and output `count`. Errors name the failing step.
+fn pipeline(input: &Value) -> Result<Value, TransformError> {
+    let mut data = array_field(input, "data")?.clone();
+    let steps = array_field(input, "steps")?;
+    if steps.is_empty() {
+        return Err("'steps' must list at least one operation".into());
+    }
+
+    let mut trace: Vec<Value> = Vec::new();
+    let mut result = Value::Null;
+    for (i, step) in steps.iter().enumerate() {
+        let operation = step
+            .get("operation")
+            .and_then(|v| v.as_str())
+            .unwrap_or("?");
+        let context = format!("Step {i} ({operation})");
+        if !step.is_object() {
+            return Err(TransformError::WrongType(format!(
+                "{context}: must be an operation object"
+            )));
+        }
+        let mut call = step.clone();
+        call["data"] = Value::Array(data);
+        result = execute(&call).map_err(|e| e.with_context(&context))?;
+        let output = result.get("data").and_then(|v| v.as_array());
+        trace.push(json!({
+            "operation": operation,
+            "count": output.map(Vec::len).or_else(|| result.get("count")?.as_u64().map(|n| n as usize)),
+        }));
+        data = match output {
+            Some(rows) => rows.clone(),
+            None if i + 1 == steps.len() => Vec::new(),
+            None => {
+                return Err(TransformError::WrongType(format!(
+                    "{context}: returned no 'data' array to pass on"
+                )))
+            }
+        };
+    }
+
+    if input
+        .get("trace")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        if let Some(obj) = result.as_object_mut() {
+            obj.insert("trace".into(), json!(trace));
+        }
+    }
+    Ok(result)
+}
+
+/// A copy of `input` with `data` parsed from its `csv` text when `format` is
+/// "csv", or `None` for the default "json" format.
+fn csv_input(input: &Value) -> Result<Option<Value>, TransformError> {
+    match input.get("format").and_then(|v| v.as_str()) {
+        None | Some("json") => return Ok(None),
+        Some("csv") => {}
+        Some(other) => return Err(format!("Unknown format: {other}. Use: json, csv").into()),
+    }
+    let text = input
+        .get("csv")
         .and_then(|v| v.as_str())
-        .ok_or("Missing 'operation' field")?;
+        .ok_or(TransformError::MissingField(
+            "'csv' text is required with \"format\": \"csv\"".into(),
+        ))?;
+    let mut csv = input.clone();
+    csv["data"] = json!(text);
+    let parsed = csv_parse(&csv)?;
 
-    match operation {
-        "stats" => compute_stats(input),
-        "filter" => filter_data(input),
-        "sort" => sort_data(input),
-        "validate" => validate_schema(input),
-        "aggregate" => aggregate_data(input),
-        _ => Err(format!(
-            "Unknown operation: {operation}. Use: stats, filter, sort, validate, aggregate"
-        )),
+    let mut input = input.clone();
+    if let Some(obj) = input.as_object_mut() {
+        obj.remove("csv");
+        obj.remove("format");
     }
+    input["data"] = parsed["data"].clone();
+    Ok(Some(input))
 }
 
-fn compute_stats(input: &Value) -> Result<Value, String> {
-    let data = input
-        .get("data")
-        .and_then(|v| v.as_array())
-        .ok_or("'data' must be an array")?;
+/// A copy of `input` with a stringified array/object `data` parsed, or `None`
+/// when `data` needs no parsing.
+fn parse_string_data(input: &Value) -> Option<Value> {
+    let text = input.get("data")?.as_str()?;
+    let data: Value = serde_json::from_str(text).ok()?;
+    if !(data.is_array() || data.is_object()) {
+        return None;
+    }
+    let mut input = input.clone();
+    input["data"] = data;
+    Some(input)
+}
+
+/// Run a registered operation, or report it as unknown.
+fn dispatch(operation: &str, input: &Value) -> Result<Value, TransformError> {
+    match REGISTRY.with(|registry| registry.borrow().get(operation)) {
+        Some(handler) => handler(input),
+        None => Err(TransformError::UnknownOperation(format!(
+            "Unknown operation: {operation}. Use: {}",
+            REGISTRY.with(|registry| registry.borrow().names().join(", "))
+        ))),
+    }
+}
+
+fn compute_stats(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    // Optionally restrict to the first or last N records, in input order
+    let head = input
+        .get("head")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize);
+    let tail = input
+        .get("tail")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize);
+    let data = match (head, tail) {
+        (Some(_), Some(_)) => return Err("Use either 'head' or 'tail', not both".into()),
+        (Some(n), None) => &data[..n.min(data.len())],
+        (None, Some(n)) => &data[data.len() - n.min(data.len())..],
+        (None, None) => &data[..],
+    };
 
     let field = input
         .get("field")
         .and_then(|v| v.as_str())
         .unwrap_or("value");
 
-    let values: Vec<f64> = data
+    // "length" computes stats over string lengths (in Unicode scalar values)
+    // instead of numeric values; non-string values are skipped.
+    let lengths = match input.get("stat").and_then(|v| v.as_str()) {
+        None | Some("value") => false,
+        Some("length") => true,
+        Some(other) => return Err(format!("Unknown stat: {other}. Use: value, length").into()),
+    };
+
+    let value_of = |item: &Value| {
+        if lengths {
+            let text = item
+                .as_str()
+                .or_else(|| field_value(item, field)?.as_str())?;
+            return Some(text.chars().count() as f64);
+        }
+        // Handle plain numbers: [10, 20, 30]
+        if let Some(n) = item.as_f64() {
+            return Some(n);
+        }
+        // Handle objects with a field: [{"value": 10}, {"score": 20}]
+        numeric_field(item, field)
+    };
+    let exclude = excluded_values(input)?;
+    // Approximate mode streams the values once instead of collecting them:
+    // moments stay exact, while median, mode and percentiles come from a
+    // reservoir of at most `sketch_size` values, so memory is bounded.
+    let approximate = input
+        .get("approximate")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let (mut moments, excluded_count) = if approximate {
+        let size = input
+            .get("sketch_size")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1000)
+            .max(1) as usize;
+        let seed = input.get("seed").and_then(|v| v.as_u64()).unwrap_or(0);
+        let mut stream = StreamingMoments::new(size, seed);
+        let mut excluded_count = 0;
+        for v in data.iter().filter_map(value_of) {
+            if exclude.as_deref().is_some_and(|e| e.contains(&v)) {
+                excluded_count += 1;
+            } else {
+                stream.push(v);
+            }
+        }
+        (stream.finish(), excluded_count)
+    } else {
+        let values: Vec<f64> = data.iter().filter_map(value_of).collect();
+        let (values, excluded_count) = drop_excluded(values, exclude.as_deref());
+        (Moments::exact(values), excluded_count)
+    };
+    let requested = requested_percentiles(input)?;
+    let weighted = weight_field(input)?
+        .map(|weight| weighted_mean(data.iter(), field, weight, exclude.as_deref()));
+    // Geometric and harmonic means, for rates and ratios
+    let ratio_means = input
+        .get("ratio_means")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    // Optional ready-made sentence the agent can quote instead of restating
+    // the numbers itself; the structured fields are unchanged.
+    let summarize = input
+        .get("summarize")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let subject = if lengths {
+        format!("{field} length")
+    } else {
+        field.to_string()
+    };
+
+    if moments.count == 0 {
+        let mut result = json!({
+            "count": 0,
+            "sum": 0,
+            "mean": 0,
+            "median": null,
+            "mode": null,
+            "min": 0,
+            "max": 0,
+        });
+        if exclude.is_some() {
+            result["excluded_count"] = json!(excluded_count);
+        }
+        if let Some(weighted) = weighted {
+            result["weighted_mean"] = weighted;
+        }
+        if ratio_means {
+            result["geometric_mean"] = Value::Null;
+            result["harmonic_mean"] = Value::Null;
+        }
+        if let Some(requested) = &requested {
+            result["percentiles"] = percentile_map(Vec::new(), requested);
+        }
+        if summarize {
+            result["summary"] = json!(format!("No numeric {subject} values to summarize."));
+        }
+        return Ok(result);
+    }
+
+    let count = moments.count as f64;
+    let (sum, mean, min, max) = (moments.sum, moments.mean, moments.min, moments.max);
+    // Population std_dev by default; `"sample": true` applies Bessel's
+    // correction, which is undefined (null) for a single value.
+    let sample = input
+        .get("sample")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let divisor = if sample { count - 1.0 } else { count };
+    let variance = mean.and(safe_div(moments.sq_dev, divisor));
+    let std_dev = variance.map(f64::sqrt);
+
+    let mut result = json!({
+        "count": count as i64,
+        "sum": sum,
+        "mean": mean,
+        "median": median(&moments.order),
+        "mode": mode(&moments.order),
+        "min": min,
+        "max": max,
+        "std_dev": std_dev,
+    });
+    if let Some(weighted) = weighted {
+        result["weighted_mean"] = weighted;
+    }
+    if ratio_means {
+        let mut notes = Vec::new();
+        let geometric = match moments.ln_sum {
+            Some(ln_sum) => {
+                let geometric = safe_div(ln_sum, count)
+                    .map(f64::exp)
+                    .filter(|g| g.is_finite());
+                if geometric.is_none() {
+                    notes.push("Geometric mean is out of range.");
+                }
+                geometric
+            }
+            None => {
+                notes.push("Geometric mean is undefined for non-positive values.");
+                None
+            }
+        };
+        let harmonic = match moments.recip_sum {
+            Some(recip_sum) => safe_div(count, recip_sum),
+            None => {
+                notes.push("Harmonic mean is undefined when a value is zero.");
+                None
+            }
+        };
+        result["geometric_mean"] = json!(geometric);
+        result["harmonic_mean"] = json!(harmonic);
+        if !notes.is_empty() {
+            result["note"] = json!(notes.join(" "));
+        }
+    }
+    if exclude.is_some() {
+        result["excluded_count"] = json!(excluded_count);
+    }
+    if let Some(requested) = &requested {
+        if approximate {
+            let sample_size = moments.order.len();
+            // Dvoretzky–Kiefer–Wolfowitz: with 95% confidence every estimate
+            // is within this fraction of the true rank. Exact when nothing
+            // was dropped.
+            let rank_error = if sample_size < moments.count {
+                ((2.0f64 / 0.05).ln() / (2.0 * sample_size as f64)).sqrt()
+            } else {
+                0.0
+            };
+            result["percentile_error"] = json!({
+                "rank_error": rank_error,
+                "confidence": 0.95,
+                "sample_size": sample_size,
+            });
+        }
+        result["percentiles"] = percentile_map(std::mem::take(&mut moments.order), requested);
+    }
+    if summarize {
+        let mut summary = format!(
+            "Across {} record{}, the mean {subject} is {} (range {}–{}",
+            count as i64,
+            if count == 1.0 { "" } else { "s" },
+            mean.map_or("undefined".into(), summary_number),
+            summary_number(min),
+            summary_number(max),
+        );
+        if let Some(sd) = std_dev {
+            summary.push_str(&format!(", std dev {}", summary_number(sd)));
+        }
+        summary.push_str(").");
+        result["summary"] = json!(summary);
+    }
+    Ok(result)
+}
+
+/// Middle value, or the mean of the two middle values for an even count.
+fn median(values: &[f64]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        n if n % 2 == 0 => Some((sorted[mid - 1] + sorted[mid]) / 2.0),
+        _ => Some(sorted[mid]),
+    }
+}
+
+/// Most frequent value; an ascending array when several tie for most frequent.
+fn mode(values: &[f64]) -> Value {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mut best: Vec<f64> = Vec::new();
+    let mut best_count = 0;
+    for run in sorted.chunk_by(|a, b| a == b) {
+        if run.len() > best_count {
+            best_count = run.len();
+            best.clear();
+        }
+        if run.len() == best_count {
+            best.push(run[0]);
+        }
+    }
+    match best.as_slice() {
+        [] => Value::Null,
+        [single] => json!(single),
+        many => json!(many),
+    }
+}
+
+/// The optional `percentiles` list, each between 0 and 100 inclusive.
+fn requested_percentiles(input: &Value) -> Result<Option<Vec<f64>>, TransformError> {
+    let Some(requested) = input.get("percentiles") else {
+        return Ok(None);
+    };
+    let requested = requested.as_array().ok_or(TransformError::WrongType(
+        "'percentiles' must be an array of numbers between 0 and 100".into(),
+    ))?;
+    requested
         .iter()
-        .filter_map(|item| {
-            // Handle plain numbers: [10, 20, 30]
-            if let Some(n) = item.as_f64() {
-                return Some(n);
+        .map(|p| match p.as_f64() {
+            Some(n) if (0.0..=100.0).contains(&n) => Ok(n),
+            _ => Err(TransformError::InvalidValue(format!(
+                "Invalid percentile: {p}. Use a number between 0 and 100"
+            ))),
+        })
+        .collect::<Result<Vec<f64>, TransformError>>()
+        .map(Some)
+}
+
+/// Percentiles of `values` keyed by the requested percentile, using linear
+/// interpolation between closest ranks (numpy's default, "R-7").
+fn percentile_map(mut values: Vec<f64>, requested: &[f64]) -> Value {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let map: serde_json::Map<String, Value> = requested
+        .iter()
+        .map(|&p| {
+            let value = (!values.is_empty()).then(|| {
+                let rank = (values.len() - 1) as f64 * p / 100.0;
+                let lower = rank.floor() as usize;
+                let upper = (lower + 1).min(values.len() - 1);
+                values[lower] + (rank - lower as f64) * (values[upper] - values[lower])
+            });
+            (p.to_string(), json!(value))
+        })
+        .collect();
+    Value::Object(map)
+}
+
+/// Stats over the collected values: moments plus the values used for
+/// median, mode and percentiles (all of them, or a bounded sample).
+struct Moments {
+    count: usize,
+    sum: f64,
+    mean: Option<f64>,
+    min: f64,
+    max: f64,
+    /// Sum of squared deviations from the mean.
+    sq_dev: f64,
+    /// Sum of logs, or `None` when any value is non-positive.
+    ln_sum: Option<f64>,
+    /// Sum of reciprocals, or `None` when any value is zero.
+    recip_sum: Option<f64>,
+    order: Vec<f64>,
+}
+
+impl Moments {
+    fn exact(values: Vec<f64>) -> Self {
+        let sum: f64 = values.iter().sum();
+        let mean = safe_div(sum, values.len() as f64);
+        Moments {
+            count: values.len(),
+            sum,
+            mean,
+            min: values.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            sq_dev: mean.map_or(f64::NAN, |m| values.iter().map(|v| (v - m).powi(2)).sum()),
+            ln_sum: values
+                .iter()
+                .all(|&v| v > 0.0)
+                .then(|| values.iter().map(|v| v.ln()).sum()),
+            recip_sum: (!values.contains(&0.0)).then(|| values.iter().map(|v| 1.0 / v).sum()),
+            order: values,
+        }
+    }
+}
+
+/// Single-pass version of [`Moments`] for approximate stats. Moments are
+/// exact (Welford's update for the spread); order statistics come from a
+/// uniform reservoir of at most `size` values (Algorithm R).
+struct StreamingMoments {
+    count: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+    running_mean: f64,
+    m2: f64,
+    ln_sum: Option<f64>,
+    recip_sum: Option<f64>,
+    reservoir: Vec<f64>,
+    size: usize,
+    rng: SplitMix64,
+}
+
+impl StreamingMoments {
+    fn new(size: usize, seed: u64) -> Self {
+        StreamingMoments {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            running_mean: 0.0,
+            m2: 0.0,
+            ln_sum: Some(0.0),
+            recip_sum: Some(0.0),
+            reservoir: Vec::new(),
+            size,
+            rng: SplitMix64(seed),
+        }
+    }
+
+    fn push(&mut self, v: f64) {
+        self.count += 1;
+        self.sum += v;
+        self.min = self.min.min(v);
+        self.max = self.max.max(v);
+        let delta = v - self.running_mean;
+        self.running_mean += delta / self.count as f64;
+        self.m2 += delta * (v - self.running_mean);
+        self.ln_sum = self.ln_sum.filter(|_| v > 0.0).map(|s| s + v.ln());
+        self.recip_sum = self.recip_sum.filter(|_| v != 0.0).map(|s| s + 1.0 / v);
+        if self.reservoir.len() < self.size {
+            self.reservoir.push(v);
+        } else {
+            let j = (self.rng.next_u64() % self.count as u64) as usize;
+            if j < self.size {
+                self.reservoir[j] = v;
             }
-            // Handle objects with a field: [{"value": 10}, {"score": 20}]
-            item.get(field).and_then(|v| v.as_f64())
+        }
+    }
+
+    fn finish(self) -> Moments {
+        Moments {
+            count: self.count,
+            sum: self.sum,
+            mean: safe_div(self.sum, self.count as f64),
+            min: self.min,
+            max: self.max,
+            sq_dev: self.m2,
+            ln_sum: self.ln_sum,
+            recip_sum: self.recip_sum,
+            order: self.reservoir,
+        }
+    }
+}
+
+/// Round to at most two decimals for prose, dropping trailing zeros.
+fn summary_number(v: f64) -> String {
+    let text = format!("{v:.2}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".into()
+    } else {
+        text.into()
+    }
+}
+
+fn filter_data(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let where_clause = input.get("where").ok_or(TransformError::MissingField(
+        "'where' clause is required for filter operation".into(),
+    ))?;
+    let condition = Predicate::parse(where_clause)?;
+    let missing_mode = Missing::parse(input)?;
+
+    let explain = input
+        .get("explain")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let mut filtered: Vec<&Value> = Vec::new();
+    let (mut missing, mut mismatched, mut failed) = (0, 0, 0);
+    for item in data {
+        match condition.evaluate_with(item, missing_mode) {
+            Outcome::Matched => filtered.push(item),
+            Outcome::MissingField => missing += 1,
+            Outcome::TypeMismatch => mismatched += 1,
+            Outcome::Failed => failed += 1,
+        }
+    }
+
+    let annotate = input
+        .get("annotate")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let rows: Vec<Value> = filtered
+        .iter()
+        .map(|item| {
+            let mut record = (*item).clone();
+            if let (true, Some(similarity), Some(obj)) = (
+                annotate,
+                condition.match_score(item),
+                record.as_object_mut(),
+            ) {
+                obj.insert("match_score".into(), json!(similarity));
+            }
+            record
         })
         .collect();
 
-    if values.is_empty() {
-        return Ok(json!({"count": 0, "sum": 0, "mean": 0, "min": 0, "max": 0}));
+    let mut result = json!({
+        "data": rows,
+        "count": rows.len(),
+        "original_count": data.len(),
+    });
+    if explain {
+        result["explain"] = json!({
+            "excluded": missing + mismatched + failed,
+            "missing_field": missing,
+            "type_mismatch": mismatched,
+            "predicate_failed": failed,
+        });
+    }
+    paginate(input, &mut result)?;
+    Ok(result)
+}
+
+/// Split records into `matched` and `unmatched` by a filter-style `where`
+/// clause in a single pass, so the two sides are always complementary.
+fn partition(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let where_clause = input.get("where").ok_or(TransformError::MissingField(
+        "'where' clause is required for partition operation".into(),
+    ))?;
+    let condition = Predicate::parse(where_clause)?;
+
+    let (matched, unmatched): (Vec<&Value>, Vec<&Value>) = data
+        .iter()
+        .partition(|item| condition.evaluate(item).is_match());
+
+    Ok(json!({
+        "matched": matched,
+        "matched_count": matched.len(),
+        "unmatched": unmatched,
+        "unmatched_count": unmatched.len(),
+    }))
+}
+
+/// A `where` clause: a single `{field, op, value}` condition, or
+/// `{"and": [...]}` / `{"or": [...]}` lists of clauses that may nest.
+/// An empty `and` matches every record; an empty `or` matches none.
+enum Predicate<'a> {
+    Leaf(Condition<'a>),
+    And(Vec<Predicate<'a>>),
+    Or(Vec<Predicate<'a>>),
+}
+
+impl<'a> Predicate<'a> {
+    fn parse(where_clause: &'a Value) -> Result<Self, TransformError> {
+        let clauses = |key: &str, list: &'a Value| -> Result<Vec<Predicate<'a>>, TransformError> {
+            list.as_array()
+                .ok_or(format!("'where.{key}' must be an array of clauses"))?
+                .iter()
+                .map(Predicate::parse)
+                .collect()
+        };
+        if let Some(list) = where_clause.get("and") {
+            return Ok(Predicate::And(clauses("and", list)?));
+        }
+        if let Some(list) = where_clause.get("or") {
+            return Ok(Predicate::Or(clauses("or", list)?));
+        }
+        Condition::parse(where_clause).map(Predicate::Leaf)
+    }
+
+    fn evaluate(&self, item: &Value) -> Outcome {
+        self.evaluate_with(item, Missing::Exclude)
+    }
+
+    /// Short-circuits: `and` reports the first clause that rejected the
+    /// record, `or` the first that accepted it.
+    fn evaluate_with(&self, item: &Value, missing: Missing) -> Outcome {
+        match self {
+            Predicate::Leaf(condition) => condition.evaluate_with(item, missing),
+            Predicate::And(clauses) => clauses
+                .iter()
+                .map(|clause| clause.evaluate_with(item, missing))
+                .find(|outcome| !outcome.is_match())
+                .unwrap_or(Outcome::Matched),
+            Predicate::Or(clauses) => {
+                let mut outcome = Outcome::Failed;
+                for (i, clause) in clauses.iter().enumerate() {
+                    match clause.evaluate_with(item, missing) {
+                        accepted if accepted.is_match() => return accepted,
+                        rejected if i == 0 => outcome = rejected,
+                        _ => {}
+                    }
+                }
+                outcome
+            }
+        }
+    }
+
+    /// Best similarity of any fuzzy clause against the record, for `annotate`.
+    fn match_score(&self, item: &Value) -> Option<f64> {
+        match self {
+            Predicate::Leaf(condition) => {
+                let text = field_value(item, condition.field)?.as_str()?;
+                condition
+                    .fuzzy_score(text)
+                    .map(|(_, similarity)| similarity)
+            }
+            Predicate::And(clauses) | Predicate::Or(clauses) => clauses
+                .iter()
+                .filter_map(|clause| clause.match_score(item))
+                .reduce(f64::max),
+        }
+    }
+}
+
+/// A single `{field, op, value}` filter clause.
+struct Condition<'a> {
+    field: &'a str,
+    op: &'a str,
+    rule: Rule,
+    /// Compare text case-insensitively (`"ignore_case": true`).
+    ignore_case: bool,
+    /// Absolute slack for numeric `==`/`!=` (`"tolerance"`).
+    tolerance: Option<f64>,
+}
+
+/// What a [`Condition`] compares the field against.
+enum Rule {
+    /// Numeric comparison against number fields.
+    Numeric(serde_json::Number),
+    /// Lexicographic (`<`, `>`, ...), exact (`==`, `!=`) or substring
+    /// (`contains`, `startswith`, `endswith`) comparison against string fields.
+    Text(String),
+    /// `fuzzy`: case-insensitive Levenshtein match against a query string,
+    /// by absolute edit distance or by similarity ratio.
+    Fuzzy {
+        query: String,
+        max_distance: Option<usize>,
+        min_similarity: f64,
+    },
+}
+
+/// How filter treats records whose field is missing or holds a different
+/// type than the clause's value (`"missing"`).
+#[derive(Clone, Copy)]
+enum Missing {
+    /// Drop the record (the default).
+    Exclude,
+    /// Keep the record regardless of the comparison.
+    Include,
+    /// Compare as if the field were 0 (numeric clauses only).
+    Zero,
+}
+
+impl Missing {
+    fn parse(input: &Value) -> Result<Self, TransformError> {
+        match input.get("missing").and_then(|v| v.as_str()) {
+            None | Some("exclude") => Ok(Missing::Exclude),
+            Some("include") => Ok(Missing::Include),
+            Some("zero") => Ok(Missing::Zero),
+            Some(other) => {
+                Err(format!("Unknown missing mode: {other}. Use: include, exclude, zero").into())
+            }
+        }
+    }
+}
+
+/// Result of testing one record against a [`Condition`].
+///
+/// By default, records whose field is missing, or holds a different type
+/// than the clause's value, never match; see [`Missing`].
+enum Outcome {
+    Matched,
+    MissingField,
+    TypeMismatch,
+    Failed,
+}
+
+impl Outcome {
+    fn is_match(&self) -> bool {
+        matches!(self, Outcome::Matched)
+    }
+}
+
+impl<'a> Condition<'a> {
+    fn parse(where_clause: &'a Value) -> Result<Self, TransformError> {
+        let field = where_clause.get("field").and_then(|v| v.as_str()).ok_or(
+            TransformError::MissingField("'where.field' is required".into()),
+        )?;
+        let op =
+            where_clause
+                .get("op")
+                .and_then(|v| v.as_str())
+                .ok_or(TransformError::MissingField(
+                    "'where.op' is required".into(),
+                ))?;
+        let rule = if op == "fuzzy" {
+            let query = where_clause.get("value").and_then(|v| v.as_str()).ok_or(
+                TransformError::WrongType("'where.value' must be a string for fuzzy".into()),
+            )?;
+            let max_distance = where_clause
+                .get("max_distance")
+                .and_then(|v| v.as_u64())
+                .map(|d| d as usize);
+            let min_similarity = where_clause
+                .get("min_similarity")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.8);
+            Rule::Fuzzy {
+                query: query.to_lowercase(),
+                max_distance,
+                min_similarity,
+            }
+        } else if matches!(op, "contains" | "startswith" | "endswith") {
+            let text = where_clause
+                .get("value")
+                .and_then(|v| v.as_str())
+                .ok_or(format!("'where.value' must be a string for {op}"))?;
+            Rule::Text(text.to_string())
+        } else {
+            match where_clause.get("value") {
+                Some(Value::String(text)) => Rule::Text(text.clone()),
+                Some(Value::Number(n)) => Rule::Numeric(n.clone()),
+                _ => return Err("'where.value' must be a number or string".into()),
+            }
+        };
+        let ignore_case = where_clause
+            .get("ignore_case")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let tolerance = match where_clause.get("tolerance") {
+            None => None,
+            Some(t) => Some(
+                t.as_f64()
+                    .filter(|t| *t >= 0.0)
+                    .ok_or("'where.tolerance' must be a non-negative number")?,
+            ),
+        };
+        Ok(Self {
+            field,
+            op,
+            rule,
+            ignore_case,
+            tolerance,
+        })
+    }
+
+    fn evaluate_with(&self, item: &Value, missing: Missing) -> Outcome {
+        let Some(raw) = field_value(item, self.field) else {
+            return self.without_value(Outcome::MissingField, missing);
+        };
+        let matched = match (&self.rule, raw) {
+            (Rule::Numeric(threshold), Value::Number(n)) => self.compare(n, threshold),
+            (Rule::Text(expected), Value::String(text)) => {
+                let (text, expected) = if self.ignore_case {
+                    (text.to_lowercase(), expected.to_lowercase())
+                } else {
+                    (text.clone(), expected.clone())
+                };
+                match self.op {
+                    "contains" => text.contains(&expected),
+                    "startswith" => text.starts_with(&expected),
+                    "endswith" => text.ends_with(&expected),
+                    _ => self.compare_ordering(text.cmp(&expected)),
+                }
+            }
+            (
+                Rule::Fuzzy {
+                    max_distance,
+                    min_similarity,
+                    ..
+                },
+                Value::String(text),
+            ) => {
+                let (distance, similarity) = self.fuzzy_score(text).unwrap_or_default();
+                match max_distance {
+                    Some(max) => distance <= *max,
+                    None => similarity >= *min_similarity,
+                }
+            }
+            _ => return self.without_value(Outcome::TypeMismatch, missing),
+        };
+        if matched {
+            Outcome::Matched
+        } else {
+            Outcome::Failed
+        }
+    }
+
+    /// Outcome for a record whose field is absent or of the wrong type.
+    /// `zero` only applies to numeric comparisons; other rules exclude.
+    fn without_value(&self, outcome: Outcome, missing: Missing) -> Outcome {
+        match (missing, &self.rule) {
+            (Missing::Include, _) => Outcome::Matched,
+            (Missing::Zero, Rule::Numeric(threshold)) if self.compare(&0.into(), threshold) => {
+                Outcome::Matched
+            }
+            (Missing::Zero, Rule::Numeric(_)) => Outcome::Failed,
+            _ => outcome,
+        }
+    }
+
+    /// Edit distance and similarity ratio (`1 - distance / longer length`)
+    /// between `text` and a fuzzy query; `None` for non-fuzzy conditions.
+    fn fuzzy_score(&self, text: &str) -> Option<(usize, f64)> {
+        let Rule::Fuzzy { query, .. } = &self.rule else {
+            return None;
+        };
+        let text = text.to_lowercase();
+        let distance = levenshtein(&text, query);
+        let longest = text.chars().count().max(query.chars().count());
+        let similarity = if longest == 0 {
+            1.0
+        } else {
+            1.0 - distance as f64 / longest as f64
+        };
+        Some((distance, similarity))
+    }
+
+    fn compare(&self, val: &serde_json::Number, threshold: &serde_json::Number) -> bool {
+        let (v, t) = (
+            val.as_f64().unwrap_or_default(),
+            threshold.as_f64().unwrap_or_default(),
+        );
+        match self.op {
+            ">" => v > t,
+            ">=" => v >= t,
+            "<" => v < t,
+            "<=" => v <= t,
+            "==" => self.numbers_equal(val, threshold),
+            "!=" => !self.numbers_equal(val, threshold),
+            _ => false,
+        }
+    }
+
+    /// Integers compare exactly; floats within a relative epsilon, or within
+    /// the clause's absolute `tolerance` when one is given.
+    fn numbers_equal(&self, a: &serde_json::Number, b: &serde_json::Number) -> bool {
+        let as_int = |n: &serde_json::Number| {
+            n.as_i64()
+                .map(i128::from)
+                .or_else(|| n.as_u64().map(i128::from))
+        };
+        let (x, y) = (
+            a.as_f64().unwrap_or_default(),
+            b.as_f64().unwrap_or_default(),
+        );
+        if let Some(tolerance) = self.tolerance {
+            return (x - y).abs() <= tolerance;
+        }
+        match (as_int(a), as_int(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => x == y || (x - y).abs() <= f64::EPSILON * x.abs().max(y.abs()),
+        }
+    }
+
+    fn compare_ordering(&self, ordering: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::*;
+        match self.op {
+            ">" => ordering == Greater,
+            ">=" => ordering != Less,
+            "<" => ordering == Less,
+            "<=" => ordering != Greater,
+            "==" => ordering == Equal,
+            "!=" => ordering != Equal,
+            _ => false,
+        }
+    }
+}
+
+/// Levenshtein edit distance over Unicode scalar values.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Sort records by `field`.
+///
+/// Optional `offset`/`limit` return one page of the sorted records.
+///
+/// By default the comparator follows the values: numbers numerically,
+/// strings lexicographically, and mixed types by type (null < bool < number
+/// < string < array < object). An explicit `type` (number, string, date or
+/// boolean) forces that comparator instead: values are parsed as the type
+/// (numeric strings as numbers, RFC 3339 strings as dates, `"true"`/`"false"`
+/// as booleans). Records missing the field, holding null, or not parseable
+/// as the declared type go last, or first with `"nulls": "first"`,
+/// regardless of direction.
+///
+/// Instead of `field`/`descending`/`type`, `by` takes an array of
+/// `{field, descending, type}` keys applied as a tie-breaking chain.
+fn sort_data(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let keys: Vec<SortSpec> = match input.get("by") {
+        Some(by) => by
+            .as_array()
+            .ok_or(TransformError::WrongType(
+                "'by' must be an array of {field, descending} objects".into(),
+            ))?
+            .iter()
+            .map(SortSpec::parse)
+            .collect::<Result<_, _>>()?,
+        None => vec![SortSpec::parse(input)?],
+    };
+
+    let nulls_first = match input.get("nulls").and_then(|v| v.as_str()) {
+        None | Some("last") => false,
+        Some("first") => true,
+        Some(other) => {
+            return Err(format!("Unknown nulls placement: {other}. Use: first, last").into())
+        }
+    };
+
+    let mut keyed: Vec<(Vec<Option<SortValue>>, &Value)> = data
+        .iter()
+        .map(|item| {
+            let values = keys
+                .iter()
+                .map(|key| SortValue::of(item, key.field, key.key_type))
+                .collect();
+            (values, item)
+        })
+        .collect();
+    // Stable sort, so each later key only breaks ties left by earlier ones
+    keyed.sort_by(|(a, _), (b, _)| {
+        keys.iter()
+            .zip(a.iter().zip(b))
+            .map(|(key, (a, b))| match (a, b) {
+                (Some(a), Some(b)) if key.descending => b.compare(a),
+                (Some(a), Some(b)) => a.compare(b),
+                (Some(_), None) if nulls_first => std::cmp::Ordering::Greater,
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) if nulls_first => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let sorted: Vec<&Value> = keyed.into_iter().map(|(_, item)| item).collect();
+
+    let mut result = json!({"data": sorted, "count": sorted.len()});
+    paginate(input, &mut result)?;
+    Ok(result)
+}
+
+/// One `{field, descending, type}` sort key.
+struct SortSpec<'a> {
+    field: &'a str,
+    descending: bool,
+    key_type: Option<&'a str>,
+}
+
+impl<'a> SortSpec<'a> {
+    fn parse(spec: &'a Value) -> Result<Self, TransformError> {
+        let field =
+            spec.get("field")
+                .and_then(|v| v.as_str())
+                .ok_or(TransformError::MissingField(
+                    "'field' is required for sort operation".into(),
+                ))?;
+        let descending = spec
+            .get("descending")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let key_type = spec.get("type").and_then(|v| v.as_str());
+        if let Some(other) =
+            key_type.filter(|t| !matches!(*t, "number" | "string" | "date" | "boolean"))
+        {
+            return Err(
+                format!("Unknown sort type: {other}. Use: number, string, date, boolean").into(),
+            );
+        }
+        Ok(Self {
+            field,
+            descending,
+            key_type,
+        })
+    }
+}
+
+/// A record's sort key: the raw value, or one parsed as a declared type.
+enum SortValue<'a> {
+    Inferred(&'a Value),
+    Typed(TypedKey),
+}
+
+impl<'a> SortValue<'a> {
+    /// `None` when the field is missing, null, or unparseable as `key_type`.
+    fn of(item: &'a Value, field: &str, key_type: Option<&str>) -> Option<Self> {
+        let value = field_value(item, field).filter(|v| !v.is_null())?;
+        match key_type {
+            None => Some(SortValue::Inferred(value)),
+            Some(key_type) => TypedKey::parse(value, key_type).map(SortValue::Typed),
+        }
+    }
+
+    fn compare(&self, other: &Self) -> std::cmp::Ordering {
+        match (self, other) {
+            (SortValue::Inferred(a), SortValue::Inferred(b)) => compare_values(a, b),
+            (SortValue::Typed(a), SortValue::Typed(b)) => a.compare(b),
+            _ => std::cmp::Ordering::Equal,
+        }
+    }
+}
+
+/// A sort key parsed as an explicitly declared type. Dates are held as
+/// seconds since the Unix epoch.
+enum TypedKey {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+impl TypedKey {
+    fn parse(value: &Value, key_type: &str) -> Option<Self> {
+        match key_type {
+            "number" => value
+                .as_f64()
+                .or_else(|| value.as_str()?.trim().parse().ok())
+                .filter(|n: &f64| n.is_finite())
+                .map(TypedKey::Number),
+            "string" => match value {
+                Value::String(s) => Some(TypedKey::Text(s.clone())),
+                Value::Null | Value::Array(_) | Value::Object(_) => None,
+                other => Some(TypedKey::Text(other.to_string())),
+            },
+            "date" => parse_rfc3339(value.as_str()?).map(TypedKey::Number),
+            "boolean" => match value {
+                Value::Bool(b) => Some(TypedKey::Bool(*b)),
+                Value::String(s) if s == "true" => Some(TypedKey::Bool(true)),
+                Value::String(s) if s == "false" => Some(TypedKey::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn compare(&self, other: &Self) -> std::cmp::Ordering {
+        match (self, other) {
+            (TypedKey::Number(a), TypedKey::Number(b)) => a.total_cmp(b),
+            (TypedKey::Text(a), TypedKey::Text(b)) => a.cmp(b),
+            (TypedKey::Bool(a), TypedKey::Bool(b)) => a.cmp(b),
+            // Keys in one sort always share a type
+            _ => std::cmp::Ordering::Equal,
+        }
+    }
+}
+
+/// Parse an RFC 3339 timestamp (`2024-03-01T12:30:00.5+02:00`, or a bare
+/// `2024-03-01` as midnight UTC) into seconds since the Unix epoch.
+fn parse_rfc3339(text: &str) -> Option<f64> {
+    let digits = |s: &str| -> Option<i64> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    };
+    let (date, time) = match text.find(['T', 't', ' ']) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    };
+    let mut parts = date.split('-');
+    let (year, month, day) = (
+        digits(parts.next()?)?,
+        digits(parts.next()?)?,
+        digits(parts.next()?)?,
+    );
+    if parts.next().is_some() || date.len() != 10 || !(1..=12).contains(&month) {
+        return None;
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = [
+        31,
+        if leap { 29 } else { 28 },
+        31,
+        30,
+        31,
+        30,
+        31,
+        31,
+        30,
+        31,
+        30,
+        31,
+    ];
+    if !(1..=month_days[month as usize - 1]).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 (Howard Hinnant's days_from_civil)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let Some(time) = time else {
+        return Some(days as f64 * 86_400.0);
+    };
+    let (clock, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(i) => (&time[..i], &time[i..]),
+        None => return None,
+    };
+    let offset_seconds = match offset {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (h, m) = offset[1..].split_once(':')?;
+            let (h, m) = (digits(h)?, digits(m)?);
+            if h > 23 || m > 59 {
+                return None;
+            }
+            sign * (h * 3600 + m * 60)
+        }
+    };
+    let (hms, fraction) = match clock.split_once('.') {
+        Some((hms, f)) => (hms, format!("0.{}", f).parse::<f64>().ok()?),
+        None => (clock, 0.0),
+    };
+    let mut hms = hms.split(':');
+    let (h, m, sec) = (
+        digits(hms.next()?)?,
+        digits(hms.next()?)?,
+        digits(hms.next()?)?,
+    );
+    if hms.next().is_some() || h > 23 || m > 59 || sec > 60 {
+        return None;
+    }
+    let seconds = days * 86_400 + h * 3600 + m * 60 + sec - offset_seconds;
+    Some(seconds as f64 + fraction)
+}
+
+fn validate_schema(input: &Value) -> Result<Value, TransformError> {
+    let data = input.get("data").ok_or(TransformError::MissingField(
+        "'data' is required for validate".into(),
+    ))?;
+    let ranges = input.get("ranges");
+    let schema = match (input.get("schema"), ranges) {
+        (Some(schema), _) => Some(schema),
+        (None, Some(_)) => None,
+        (None, None) => {
+            return Err(TransformError::MissingField(
+                "'schema' is required for validate".into(),
+            ))
+        }
+    };
+
+    let mut errors: Vec<String> = Vec::new();
+    if let Some(schema) = schema {
+        check_schema(data, schema, "", &mut errors)?;
+    }
+
+    let mut result = serde_json::Map::new();
+    if let Some(ranges) = ranges {
+        let ranges = ranges.as_object().ok_or(TransformError::WrongType(
+            "'ranges' must be an object mapping fields to {min, max}".into(),
+        ))?;
+        let violations = check_ranges(data, ranges)?;
+        for v in &violations {
+            errors.push(format!(
+                "/{}/{}: {}",
+                v["index"],
+                v["field"].as_str().unwrap_or_default(),
+                v["message"].as_str().unwrap_or_default()
+            ));
+        }
+        result.insert("range_violations".into(), json!(violations));
+    }
+
+    result.insert("valid".into(), json!(errors.is_empty()));
+    result.insert("error_count".into(), json!(errors.len()));
+    result.insert("errors".into(), json!(errors));
+    Ok(Value::Object(result))
+}
+
+/// Check `data` against `schema`, recursing into `properties` so nested
+/// objects get their own type and `required` checks, and into `items` for
+/// every array element (`minItems`/`maxItems` bound the length). Strings honor
+/// `minLength`/`maxLength` (in characters) and a `pattern` regex; an invalid
+/// pattern is an error rather than a pass. Numbers honor `minimum`,
+/// `maximum` and their `exclusive*` forms; `integer` rejects fractions. An
+/// `enum` at any level requires a value equal to one of its members. Errors are prefixed
+/// with a JSON-pointer path (`/user/age: ...`); root errors have no prefix.
+fn check_schema(
+    data: &Value,
+    schema: &Value,
+    path: &str,
+    errors: &mut Vec<String>,
+) -> Result<(), TransformError> {
+    let mut fail = |message: String| {
+        if path.is_empty() {
+            errors.push(message);
+        } else {
+            errors.push(format!("{path}: {message}"));
+        }
+    };
+    if let Some(allowed) = schema.get("enum").and_then(|v| v.as_array()) {
+        if !allowed.contains(data) {
+            let options: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            fail(format!("{data} is not one of: {}", options.join(", ")));
+        }
+    }
+    let schema_type = schema.get("type").and_then(|v| v.as_str()).unwrap_or("any");
+
+    match schema_type {
+        "object" => {
+            let Some(obj) = data.as_object() else {
+                fail(format!("Expected object, got {}", type_name(data)));
+                return Ok(());
+            };
+            if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+                for field_name in required.iter().filter_map(|v| v.as_str()) {
+                    if !obj.contains_key(field_name) {
+                        fail(format!("Missing required field: {field_name}"));
+                    }
+                }
+            }
+            if let Some(properties) = schema.get("properties").and_then(|v| v.as_object()) {
+                for (name, sub_schema) in properties {
+                    if let Some(value) = obj.get(name) {
+                        let child = format!("{path}/{}", pointer_escape(name));
+                        check_schema(value, sub_schema, &child, errors)?;
+                    }
+                }
+            }
+        }
+        "array" => {
+            let Some(items) = data.as_array() else {
+                fail(format!("Expected array, got {}", type_name(data)));
+                return Ok(());
+            };
+            if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+                if (items.len() as u64) < min {
+                    fail(format!(
+                        "Expected at least {min} items, got {}",
+                        items.len()
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+                if items.len() as u64 > max {
+                    fail(format!("Expected at most {max} items, got {}", items.len()));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_schema(item, item_schema, &format!("{path}/{i}"), errors)?;
+                }
+            }
+        }
+        "string" => {
+            let Some(text) = data.as_str() else {
+                fail(format!("Expected string, got {}", type_name(data)));
+                return Ok(());
+            };
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                if length < min {
+                    fail(format!(
+                        "String is shorter than minLength {min} (length {length})"
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                if length > max {
+                    fail(format!(
+                        "String is longer than maxLength {max} (length {length})"
+                    ));
+                }
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(|v| v.as_str()) {
+                let re = Regex::new(pattern).map_err(|e| {
+                    format!("Invalid pattern '{pattern}' at {}: {e}", display_path(path))
+                })?;
+                if !re.is_match(text) {
+                    fail(format!("String does not match pattern '{pattern}'"));
+                }
+            }
+        }
+        "number" | "integer" => {
+            let Some(n) = data.as_f64() else {
+                fail(format!("Expected number, got {}", type_name(data)));
+                return Ok(());
+            };
+            if schema_type == "integer" && n.fract() != 0.0 {
+                fail(format!("Expected integer, got {n}"));
+            }
+            let bound = |key: &str| schema.get(key).and_then(|v| v.as_f64());
+            if let Some(min) = bound("minimum").filter(|&min| n < min) {
+                fail(format!("{n} is below minimum {min}"));
+            }
+            if let Some(min) = bound("exclusiveMinimum").filter(|&min| n <= min) {
+                fail(format!("{n} is not above exclusiveMinimum {min}"));
+            }
+            if let Some(max) = bound("maximum").filter(|&max| n > max) {
+                fail(format!("{n} is above maximum {max}"));
+            }
+            if let Some(max) = bound("exclusiveMaximum").filter(|&max| n >= max) {
+                fail(format!("{n} is not below exclusiveMaximum {max}"));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The JSON pointer for error messages, with `/` standing in for the root.
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+/// Escape a key for use as a JSON-pointer segment (RFC 6901).
+fn pointer_escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Check per-field `{min, max}` bounds (both inclusive, either optional).
+///
+/// `data` may be a single record or an array of records. Fields that are
+/// absent are skipped — use `required` for presence — but present values that
+/// aren't numbers are reported.
+fn check_ranges(
+    data: &Value,
+    ranges: &serde_json::Map<String, Value>,
+) -> Result<Vec<Value>, TransformError> {
+    let mut bounds: Vec<(&str, Option<f64>, Option<f64>)> = Vec::new();
+    for (field, spec) in ranges {
+        let min = spec.get("min").and_then(|v| v.as_f64());
+        let max = spec.get("max").and_then(|v| v.as_f64());
+        if min.is_none() && max.is_none() {
+            return Err(format!("'ranges.{field}' must have a numeric 'min' or 'max'").into());
+        }
+        bounds.push((field, min, max));
+    }
+
+    let records: Vec<&Value> = match data {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+
+    let mut violations = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        for (field, min, max) in &bounds {
+            let Some(raw) = field_value(record, field) else {
+                continue;
+            };
+            let message = match raw.as_f64() {
+                None => Some(format!("expected number, got {}", type_name(raw))),
+                Some(v) if min.is_some_and(|m| v < m) => {
+                    Some(format!("{v} is below minimum {}", min.unwrap_or_default()))
+                }
+                Some(v) if max.is_some_and(|m| v > m) => {
+                    Some(format!("{v} is above maximum {}", max.unwrap_or_default()))
+                }
+                Some(_) => None,
+            };
+            if let Some(message) = message {
+                violations.push(json!({
+                    "index": index,
+                    "field": field,
+                    "value": raw,
+                    "message": message,
+                }));
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// Aggregate a numeric `field` with the `compute` ops (sum, mean, min, max,
+/// range, population variance, sum_sq). Unrecognized ops are listed under
+/// `errors`. With a `weight` field, `weighted_mean` is added alongside.
+///
+/// With `group_by`, results are returned per distinct value of that field
+/// under `groups` (items missing it are grouped under null), and
+/// `"include_total": true` adds the same aggregates over all records under
+/// `total`.
+///
+/// With no numeric values, `count` is 0 and every requested aggregate is
+/// null.
+fn aggregate_data(input: &Value) -> Result<Value, TransformError> {
+    let group_by = input
+        .get("group_by")
+        .map(|v| {
+            v.as_str().ok_or(TransformError::WrongType(
+                "'group_by' must be a field name".into(),
+            ))
+        })
+        .transpose()?;
+    aggregate_grouped(input, group_by)
+}
+
+/// Shared body of aggregate and group_by: the aggregates over all of
+/// `data`, or per `group_by` group when one is given.
+fn aggregate_grouped(input: &Value, group_by: Option<&str>) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let compute =
+        input
+            .get("compute")
+            .and_then(|v| v.as_array())
+            .ok_or(TransformError::WrongType(
+                "'compute' must be an array of operation names".into(),
+            ))?;
+
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+
+    // Sentinel values (e.g. -1 for "not measured") are dropped before
+    // aggregating and counted per group in `excluded_count`
+    let exclude = excluded_values(input)?;
+    let weight = weight_field(input)?;
+    let aggregate = |items: Vec<&Value>| {
+        let values = field_values(items.iter().copied(), field);
+        let (values, excluded_count) = drop_excluded(values, exclude.as_deref());
+        let mut result = aggregate_values(&values, compute);
+        if let Some(weight) = weight {
+            let weighted = weighted_mean(items.into_iter(), field, weight, exclude.as_deref());
+            result.insert("weighted_mean".into(), weighted);
+        }
+        if exclude.is_some() {
+            result.insert("excluded_count".into(), json!(excluded_count));
+        }
+        result
+    };
+
+    let errors = compute_errors(compute);
+    let Some(group_by) = group_by else {
+        let mut result = aggregate(data.iter().collect());
+        if !errors.is_empty() {
+            result.insert("errors".into(), json!(errors));
+        }
+        return Ok(Value::Object(result));
+    };
+
+    let groups: Vec<Value> = group_records(data, group_by)
+        .into_iter()
+        .map(|(key, items)| {
+            let mut group = serde_json::Map::new();
+            group.insert("key".into(), key);
+            group.extend(aggregate(items));
+            Value::Object(group)
+        })
+        .collect();
+
+    let mut result = json!({
+        "groups": groups,
+        "group_count": groups.len(),
+    });
+    if input
+        .get("include_total")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        result["total"] = Value::Object(aggregate(data.iter().collect()));
+    }
+    if !errors.is_empty() {
+        result["errors"] = json!(errors);
+    }
+    Ok(result)
+}
+
+/// Aggregate `field` over only the records matching a `where` clause
+/// (SUMIF/AVERAGEIF-style), using the same predicates as filter.
+fn conditional_aggregate(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let where_clause = input.get("where").ok_or(TransformError::MissingField(
+        "'where' clause is required for cond_aggregate operation".into(),
+    ))?;
+    let condition = Predicate::parse(where_clause)?;
+    let compute =
+        input
+            .get("compute")
+            .and_then(|v| v.as_array())
+            .ok_or(TransformError::WrongType(
+                "'compute' must be an array of operation names".into(),
+            ))?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+
+    let matching: Vec<&Value> = data
+        .iter()
+        .filter(|item| condition.evaluate(item).is_match())
+        .collect();
+
+    let mut result = aggregate_values(&field_values(matching.iter().copied(), field), compute);
+    result.insert("matched".into(), json!(matching.len()));
+    result.insert("original_count".into(), json!(data.len()));
+    let errors = compute_errors(compute);
+    if !errors.is_empty() {
+        result.insert("errors".into(), json!(errors));
+    }
+    Ok(Value::Object(result))
+}
+
+/// Aggregates understood in a `compute` list.
+const COMPUTE_OPS: &[&str] = &["sum", "mean", "min", "max", "range", "variance", "sum_sq"];
+
+/// One message per `compute` entry that isn't a known aggregate, so typos
+/// like "average" are reported instead of silently dropped.
+fn compute_errors(compute: &[Value]) -> Vec<String> {
+    compute
+        .iter()
+        .filter(|op| !op.as_str().is_some_and(|name| COMPUTE_OPS.contains(&name)))
+        .map(|op| {
+            let name = op.as_str().map_or_else(|| op.to_string(), String::from);
+            format!(
+                "Unknown compute operation: {name}. Use: {}",
+                COMPUTE_OPS.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// `count` plus each `compute` aggregate over `values`; aggregates of no
+/// values are null rather than 0 or ±Infinity. Unknown names are skipped
+/// here and reported through [`compute_errors`].
+fn aggregate_values(values: &[f64], compute: &[Value]) -> serde_json::Map<String, Value> {
+    let mut result = serde_json::Map::new();
+    result.insert("count".into(), json!(values.len()));
+
+    for op in compute {
+        if let Some(op_name) = op.as_str() {
+            match op_name {
+                _ if values.is_empty() && COMPUTE_OPS.contains(&op_name) => {
+                    result.insert(op_name.into(), Value::Null);
+                }
+                "sum" => {
+                    result.insert("sum".into(), json!(values.iter().sum::<f64>()));
+                }
+                "mean" => {
+                    let mean = safe_div(values.iter().sum::<f64>(), values.len() as f64);
+                    result.insert("mean".into(), json!(mean));
+                }
+                "min" => {
+                    result.insert(
+                        "min".into(),
+                        json!(values.iter().cloned().fold(f64::INFINITY, f64::min)),
+                    );
+                }
+                "max" => {
+                    result.insert(
+                        "max".into(),
+                        json!(values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)),
+                    );
+                }
+                "range" => {
+                    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                    let range = Some(max - min).filter(|r| r.is_finite());
+                    result.insert("range".into(), json!(range));
+                }
+                "variance" => {
+                    // Population variance, as in stats
+                    let count = values.len() as f64;
+                    let variance = safe_div(values.iter().sum::<f64>(), count).and_then(|mean| {
+                        safe_div(values.iter().map(|v| (v - mean).powi(2)).sum(), count)
+                    });
+                    result.insert("variance".into(), json!(variance));
+                }
+                "sum_sq" => {
+                    result.insert(
+                        "sum_sq".into(),
+                        json!(values.iter().map(|v| v * v).sum::<f64>()),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    result
+}
+
+/// The optional `exclude_values` list of sentinel numbers.
+fn excluded_values(input: &Value) -> Result<Option<Vec<f64>>, TransformError> {
+    input
+        .get("exclude_values")
+        .map(|v| {
+            v.as_array()
+                .and_then(|arr| arr.iter().map(|x| x.as_f64()).collect())
+                .ok_or_else(|| {
+                    TransformError::WrongType("'exclude_values' must be an array of numbers".into())
+                })
+        })
+        .transpose()
+}
+
+/// `sum(value * weight) / sum(weight)` over records where both `field` and
+/// the `weight` field are numeric (and the value isn't an excluded
+/// sentinel); null when the total weight is zero.
+fn weighted_mean<'a>(
+    items: impl Iterator<Item = &'a Value>,
+    field: &str,
+    weight: &str,
+    exclude: Option<&[f64]>,
+) -> Value {
+    let (weighted_sum, total_weight) = items
+        .filter_map(|item| Some((numeric_field(item, field)?, numeric_field(item, weight)?)))
+        .filter(|(value, _)| !exclude.is_some_and(|exclude| exclude.contains(value)))
+        .fold((0.0, 0.0), |(sum, total), (value, w)| {
+            (sum + value * w, total + w)
+        });
+    json!(safe_div(weighted_sum, total_weight))
+}
+
+/// The optional `weight` parameter: the field holding each record's weight.
+fn weight_field(input: &Value) -> Result<Option<&str>, TransformError> {
+    input
+        .get("weight")
+        .map(|v| {
+            v.as_str().ok_or(TransformError::WrongType(
+                "'weight' must be a field name".into(),
+            ))
+        })
+        .transpose()
+}
+
+/// Drop values exactly equal to an excluded sentinel, returning the rest
+/// and how many were dropped.
+fn drop_excluded(values: Vec<f64>, exclude: Option<&[f64]>) -> (Vec<f64>, usize) {
+    let Some(exclude) = exclude else {
+        return (values, 0);
+    };
+    let before = values.len();
+    let kept: Vec<f64> = values
+        .into_iter()
+        .filter(|v| !exclude.contains(v))
+        .collect();
+    let dropped = before - kept.len();
+    (kept, dropped)
+}
+
+/// Per-group rollup: aggregate with `group_by` taken from `by` (missing →
+/// null), so `weight`, `exclude_values` and `include_total` behave the same.
+/// Keys of any JSON type are supported; groups keep first-seen order.
+fn group_by(input: &Value) -> Result<Value, TransformError> {
+    let by = input
+        .get("by")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'by' field is required for group_by operation".into(),
+        ))?;
+    aggregate_grouped(input, Some(by))
+}
+
+/// Row counts: the total, per-value counts of a `by` field (an object keyed
+/// by value, with missing values under "null"), or with `distinct` the number
+/// of distinct values of that field — per `by` group when both are given.
+fn count_rows(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field_param = |key: &str| match input.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| TransformError::WrongType(format!("'{key}' must be a field name"))),
+    };
+    let by = field_param("by")?;
+    let distinct = field_param("distinct")?;
+    let tally = |items: &[&Value]| match distinct {
+        Some(field) => {
+            let mut seen = std::collections::HashSet::new();
+            items
+                .iter()
+                .filter(|item| {
+                    seen.insert(canonical_json(
+                        field_value(item, field).unwrap_or(&Value::Null),
+                    ))
+                })
+                .count()
+        }
+        None => items.len(),
+    };
+
+    let all: Vec<&Value> = data.iter().collect();
+    let mut result = json!({"count": tally(&all)});
+    if distinct.is_some() {
+        result["total"] = json!(data.len());
+    }
+    if let Some(by) = by {
+        let counts: serde_json::Map<String, Value> = group_records(data, by)
+            .into_iter()
+            .map(|(key, items)| (count_key(&key), json!(tally(&items))))
+            .collect();
+        result["counts"] = Value::Object(counts);
+    }
+    Ok(result)
+}
+
+/// Object key for a grouped value: strings as-is, anything else (including
+/// null for missing values) as its JSON text.
+fn count_key(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Numeric values of `field`, accepting plain numbers as well as records.
+fn field_values<'a>(items: impl Iterator<Item = &'a Value>, field: &str) -> Vec<f64> {
+    items
+        .filter_map(|item| {
+            // Handle plain numbers: [10, 20, 30]
+            if let Some(n) = item.as_f64() {
+                return Some(n);
+            }
+            // Handle objects with a field: [{"value": 10}, {"score": 20}]
+            numeric_field(item, field)
+        })
+        .collect()
+}
+
+/// Group records by the value of `by` (missing → null), in first-seen order.
+fn group_records<'a>(data: &'a [Value], by: &str) -> Vec<(Value, Vec<&'a Value>)> {
+    let mut groups: Vec<(Value, Vec<&'a Value>)> = Vec::new();
+    let mut index: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for item in data {
+        let key = field_value(item, by).cloned().unwrap_or(Value::Null);
+        let key_str = canonical_json(&key);
+        match index.get(&key_str) {
+            Some(&i) => groups[i].1.push(item),
+            None => {
+                index.insert(key_str, groups.len());
+                groups.push((key, vec![item]));
+            }
+        }
+    }
+    groups
+}
+
+/// Find the `n` records closest to a `target` over a set of numeric `fields`.
+///
+/// Distance is Euclidean by default, or cosine (`1 - cosine similarity`) with
+/// `"metric": "cosine"`. With `"normalize": true` each field is min-max scaled
+/// over `data` first so fields on larger scales don't dominate. Records missing
+/// any of the fields are skipped.
+fn nearest_records(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let target = input
+        .get("target")
+        .filter(|v| v.is_object())
+        .ok_or(TransformError::WrongType(
+            "'target' must be an object".into(),
+        ))?;
+
+    let fields: Vec<&str> = input
+        .get("fields")
+        .and_then(|v| v.as_array())
+        .ok_or(TransformError::WrongType(
+            "'fields' must be an array of field names".into(),
+        ))?
+        .iter()
+        .filter_map(|f| f.as_str())
+        .collect();
+    if fields.is_empty() {
+        return Err("'fields' must name at least one numeric field".into());
+    }
+
+    let n = input.get("n").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
+    let metric = input
+        .get("metric")
+        .and_then(|v| v.as_str())
+        .unwrap_or("euclidean");
+    if metric != "euclidean" && metric != "cosine" {
+        return Err(format!("Unknown metric: {metric}. Use: euclidean, cosine").into());
+    }
+    let normalize = input
+        .get("normalize")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("distance");
+
+    let target_vec: Vec<f64> = fields
+        .iter()
+        .map(|f| numeric_field(target, f).ok_or_else(|| format!("'target.{f}' must be a number")))
+        .collect::<Result<_, _>>()?;
+
+    // Feature vectors for every record that has all the requested fields
+    let candidates: Vec<(usize, Vec<f64>)> = data
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| {
+            let vec: Option<Vec<f64>> = fields.iter().map(|f| numeric_field(item, f)).collect();
+            vec.map(|v| (i, v))
+        })
+        .collect();
+
+    // Per-field (min, range) used to scale both the target and the candidates
+    let scale = |vec: &[f64], bounds: &[(f64, f64)]| -> Vec<f64> {
+        vec.iter()
+            .zip(bounds)
+            .map(|(x, (min, range))| safe_div(x - min, *range).unwrap_or(0.0))
+            .collect::<Vec<f64>>()
+    };
+    let (target_vec, candidates) = if normalize {
+        let bounds: Vec<(f64, f64)> = (0..fields.len())
+            .map(|j| {
+                let min = candidates
+                    .iter()
+                    .map(|(_, v)| v[j])
+                    .fold(f64::INFINITY, f64::min);
+                let max = candidates
+                    .iter()
+                    .map(|(_, v)| v[j])
+                    .fold(f64::NEG_INFINITY, f64::max);
+                (min, max - min)
+            })
+            .collect();
+        let scaled = candidates
+            .iter()
+            .map(|(i, v)| (*i, scale(v, &bounds)))
+            .collect();
+        (scale(&target_vec, &bounds), scaled)
+    } else {
+        (target_vec, candidates)
+    };
+
+    let mut distances: Vec<(usize, f64)> = candidates
+        .iter()
+        .filter_map(|(i, v)| {
+            let d = if metric == "cosine" {
+                cosine_distance(&target_vec, v)?
+            } else {
+                euclidean_distance(&target_vec, v)
+            };
+            Some((*i, d))
+        })
+        .collect();
+    // Stable sort keeps input order among equally distant records
+    distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    distances.truncate(n);
+
+    let nearest: Vec<Value> = distances
+        .iter()
+        .map(|(i, d)| {
+            let mut record = data[*i].clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(d));
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({
+        "data": nearest,
+        "count": nearest.len(),
+        "metric": metric,
+        "candidates": candidates.len(),
+    }))
+}
+
+/// Deep-merge an `override` object into a `base` object.
+///
+/// Nested objects are merged recursively; any other value in `override` wins.
+/// `array_strategy` decides whether array values are replaced (default) or
+/// concatenated (`base` items first).
+fn merge_objects(input: &Value) -> Result<Value, TransformError> {
+    let base = input
+        .get("base")
+        .filter(|v| v.is_object())
+        .ok_or(TransformError::WrongType("'base' must be an object".into()))?;
+    let overrides =
+        input
+            .get("override")
+            .filter(|v| v.is_object())
+            .ok_or(TransformError::WrongType(
+                "'override' must be an object".into(),
+            ))?;
+
+    let concat_arrays = match input
+        .get("array_strategy")
+        .and_then(|v| v.as_str())
+        .unwrap_or("replace")
+    {
+        "replace" => false,
+        "concat" => true,
+        other => {
+            return Err(format!("Unknown array_strategy: {other}. Use: replace, concat").into())
+        }
+    };
+
+    let mut merged = base.clone();
+    deep_merge(&mut merged, overrides, concat_arrays);
+    Ok(json!({"data": merged}))
+}
+
+fn deep_merge(base: &mut Value, overrides: &Value, concat_arrays: bool) {
+    match (base, overrides) {
+        (Value::Object(base_map), Value::Object(override_map)) => {
+            for (key, value) in override_map {
+                match base_map.get_mut(key) {
+                    Some(existing) => deep_merge(existing, value, concat_arrays),
+                    None => {
+                        base_map.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(base_items), Value::Array(override_items)) if concat_arrays => {
+            base_items.extend(override_items.iter().cloned());
+        }
+        (base, value) => *base = value.clone(),
+    }
+}
+
+/// Period-over-period percentage change of `field`, written to `output`.
+///
+/// Each record gets `(current - previous) / previous * 100`, comparing against
+/// the record `periods` positions earlier. The first `periods` records, records
+/// without a numeric value on either side, and zero previous values yield null.
+/// With `"cagr": true` the compound growth rate per period between the first
+/// and last numeric values is reported as a summary.
+fn growth_rate(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for growth_rate operation".into(),
+        ))?;
+    let periods = input.get("periods").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    if periods == 0 {
+        return Err("'periods' must be at least 1".into());
+    }
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("growth_rate");
+
+    let values: Vec<Option<f64>> = data.iter().map(|item| numeric_field(item, field)).collect();
+
+    let rows: Vec<Value> = data
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let growth = i
+                .checked_sub(periods)
+                .and_then(|prev| Some((values[prev]?, values[i]?)))
+                .and_then(|(previous, current)| safe_div(current - previous, previous))
+                .map(|ratio| ratio * 100.0);
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(growth));
+            }
+            record
+        })
+        .collect();
+
+    let mut result = json!({"data": rows, "count": rows.len()});
+    if input.get("cagr").and_then(|v| v.as_bool()).unwrap_or(false) {
+        result["cagr"] = json!(compound_growth_rate(&values));
+    }
+    Ok(result)
+}
+
+/// Compound growth rate (%) per step between the first and last known values.
+fn compound_growth_rate(values: &[Option<f64>]) -> Option<f64> {
+    let (first_idx, first) = values
+        .iter()
+        .enumerate()
+        .find_map(|(i, v)| v.map(|v| (i, v)))?;
+    let (last_idx, last) = values
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(i, v)| v.map(|v| (i, v)))?;
+    if first <= 0.0 || last < 0.0 {
+        return None;
+    }
+    let exponent = safe_div(1.0, (last_idx - first_idx) as f64)?;
+    let growth = safe_div(last, first)?.powf(exponent);
+    growth.is_finite().then_some((growth - 1.0) * 100.0)
+}
+
+/// Most decimal places format_numbers will write.
+const MAX_DECIMALS: u64 = 20;
+
+/// Render numeric `fields` as display strings in parallel `<field>_formatted` fields.
+///
+/// Styles: `thousands` (`1,234,567.89`), `currency` (`$1,234,567.89`),
+/// `compact` (`1.23M`, prefixed with `currency_symbol` when given) and
+/// `percent` (fractions, `0.256` → `25.60%`). `decimals` defaults to 2
+/// (at most `MAX_DECIMALS`).
+/// The original numeric fields are left untouched.
+fn format_numbers(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let fields: Vec<&str> = input
+        .get("fields")
+        .and_then(|v| v.as_array())
+        .ok_or(TransformError::WrongType(
+            "'fields' must be an array of field names".into(),
+        ))?
+        .iter()
+        .filter_map(|f| f.as_str())
+        .collect();
+
+    let style = input
+        .get("style")
+        .and_then(|v| v.as_str())
+        .unwrap_or("thousands");
+    if !matches!(style, "thousands" | "currency" | "compact" | "percent") {
+        return Err(
+            format!("Unknown style: {style}. Use: thousands, currency, compact, percent").into(),
+        );
+    }
+    let decimals = input.get("decimals").and_then(|v| v.as_u64()).unwrap_or(2);
+    if decimals > MAX_DECIMALS {
+        return Err(format!("'decimals' must be at most {MAX_DECIMALS}").into());
+    }
+    let decimals = decimals as usize;
+    let explicit_symbol = input.get("currency_symbol").and_then(|v| v.as_str());
+    let symbol = explicit_symbol.unwrap_or("$");
+
+    let format = |n: f64| -> String {
+        let sign = if n < 0.0 { "-" } else { "" };
+        match style {
+            "currency" => format!("{sign}{symbol}{}", group_thousands(n.abs(), decimals)),
+            "compact" => format!(
+                "{sign}{}{}",
+                explicit_symbol.unwrap_or(""),
+                compact_number(n.abs(), decimals)
+            ),
+            "percent" => format!("{:.decimals$}%", n * 100.0),
+            _ => format!("{sign}{}", group_thousands(n.abs(), decimals)),
+        }
+    };
+
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                for field in &fields {
+                    if let Some(n) = numeric_field(item, field) {
+                        obj.insert(format!("{field}_formatted"), json!(format(n)));
+                    }
+                }
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Format a non-negative number with comma thousands separators.
+fn group_thousands(n: f64, decimals: usize) -> String {
+    let fixed = format!("{n:.decimals$}");
+    let (int_part, frac_part) = match fixed.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (fixed.as_str(), None),
+    };
+    let mut grouped = String::with_capacity(int_part.len() + int_part.len() / 3);
+    for (i, c) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    match frac_part {
+        Some(f) => format!("{grouped}.{f}"),
+        None => grouped,
+    }
+}
+
+/// Format a non-negative number with a K/M/B/T magnitude suffix.
+///
+/// The suffix is picked after rounding to `decimals`, so 999999 becomes
+/// `1.00M` rather than `1000.00K`.
+fn compact_number(n: f64, decimals: usize) -> String {
+    const SUFFIXES: [(f64, &str); 5] = [(1.0, ""), (1e3, "K"), (1e6, "M"), (1e9, "B"), (1e12, "T")];
+    let mut i = SUFFIXES
+        .iter()
+        .rposition(|(scale, _)| n >= *scale)
+        .unwrap_or(0);
+    let rounded = |i: usize| format!("{:.decimals$}", n / SUFFIXES[i].0);
+    if i + 1 < SUFFIXES.len() && rounded(i).parse::<f64>().is_ok_and(|v| v >= 1000.0) {
+        i += 1;
+    }
+    format!("{}{}", rounded(i), SUFFIXES[i].1)
+}
+
+/// Report groups of records that share the same key, without removing any.
+///
+/// The key is the values of the `by` fields (missing fields count as null),
+/// or the whole record when `by` is omitted. Only groups with two or more
+/// members are returned, in order of first occurrence. `duplicate_count` is
+/// the number of records that repeat an earlier one.
+fn find_duplicates(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let by: Option<Vec<&str>> = match input.get("by") {
+        Some(v) => Some(
+            v.as_array()
+                .ok_or(TransformError::WrongType(
+                    "'by' must be an array of field names".into(),
+                ))?
+                .iter()
+                .filter_map(|f| f.as_str())
+                .collect(),
+        ),
+        None => None,
+    };
+
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: std::collections::HashMap<String, Vec<usize>> =
+        std::collections::HashMap::new();
+    for (i, item) in data.iter().enumerate() {
+        let key = record_key(item, by.as_deref());
+        groups
+            .entry(key.clone())
+            .or_insert_with(|| {
+                order.push(key);
+                Vec::new()
+            })
+            .push(i);
+    }
+
+    let duplicates: Vec<Value> = order
+        .iter()
+        .filter_map(|key| {
+            let indices = &groups[key];
+            if indices.len() < 2 {
+                return None;
+            }
+            let first = &data[indices[0]];
+            let key_value = match &by {
+                Some(fields) => Value::Object(
+                    fields
+                        .iter()
+                        .map(|f| {
+                            let value = field_value(first, f).cloned().unwrap_or(Value::Null);
+                            (f.to_string(), value)
+                        })
+                        .collect(),
+                ),
+                None => first.clone(),
+            };
+            Some(json!({
+                "key": key_value,
+                "count": indices.len(),
+                "indices": indices,
+                "records": indices.iter().map(|i| &data[*i]).collect::<Vec<_>>(),
+            }))
+        })
+        .collect();
+
+    let duplicate_count: usize = duplicates
+        .iter()
+        .map(|g| g["count"].as_u64().unwrap_or(0) as usize - 1)
+        .sum();
+
+    Ok(json!({
+        "groups": duplicates,
+        "group_count": duplicates.len(),
+        "duplicate_count": duplicate_count,
+        "original_count": data.len(),
+    }))
+}
+
+/// Drop duplicate records, keeping the first occurrence in input order.
+/// Records are duplicates when their `fields` match, or when they are equal
+/// as a whole if no fields are given.
+fn distinct(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let fields: Option<Vec<&str>> = match input.get("fields") {
+        Some(v) => Some(
+            v.as_array()
+                .ok_or(TransformError::WrongType(
+                    "'fields' must be an array of field names".into(),
+                ))?
+                .iter()
+                .filter_map(|f| f.as_str())
+                .collect(),
+        ),
+        None => None,
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    let kept: Vec<&Value> = data
+        .iter()
+        .filter(|item| seen.insert(record_key(item, fields.as_deref())))
+        .collect();
+
+    Ok(json!({
+        "data": kept,
+        "count": kept.len(),
+        "removed": data.len() - kept.len(),
+    }))
+}
+
+/// Canonical string key for a record, over `fields` or the whole value.
+fn record_key(item: &Value, fields: Option<&[&str]>) -> String {
+    match fields {
+        Some(fields) => {
+            let parts: Vec<&Value> = fields
+                .iter()
+                .map(|f| field_value(item, f).unwrap_or(&Value::Null))
+                .collect();
+            canonical_json(&Value::Array(parts.into_iter().cloned().collect()))
+        }
+        None => canonical_json(item),
+    }
+}
+
+/// Pearson correlation of `x` and `y` over records where both are numeric.
+///
+/// `n` is the number of pairs used; `r` is null with fewer than two pairs or
+/// when either field has zero variance.
+fn correlate(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let x = input
+        .get("x")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'x' field is required for correlate operation".into(),
+        ))?;
+    let y = input
+        .get("y")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'y' field is required for correlate operation".into(),
+        ))?;
+
+    let pairs = paired_values(data, x, y);
+    let n = pairs.len();
+    if n < 2 {
+        return Ok(json!({"r": null, "n": n}));
+    }
+
+    let count = n as f64;
+    let (Some(mean_x), Some(mean_y)) = (
+        safe_div(pairs.iter().map(|(x, _)| x).sum(), count),
+        safe_div(pairs.iter().map(|(_, y)| y).sum(), count),
+    ) else {
+        return Ok(json!({"r": null, "n": n}));
+    };
+    let sxx: f64 = pairs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let syy: f64 = pairs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    let sxy: f64 = pairs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let r = safe_div(sxy, (sxx * syy).sqrt()).map(|r| r.clamp(-1.0, 1.0));
+
+    Ok(json!({"r": r, "n": n}))
+}
+
+/// Least-squares fit of `y` against `x` over records where both are numeric.
+///
+/// Returns null coefficients when there are fewer than two pairs or `x` has
+/// zero variance; `r_squared` is also null when `y` is constant.
+fn linear_regression(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let x = input
+        .get("x")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'x' field is required for linreg operation".into(),
+        ))?;
+    let y = input
+        .get("y")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'y' field is required for linreg operation".into(),
+        ))?;
+
+    let pairs = paired_values(data, x, y);
+    let n = pairs.len();
+    let null = json!({"slope": null, "intercept": null, "r_squared": null, "n": n});
+    if n < 2 {
+        return Ok(null);
+    }
+
+    let count = n as f64;
+    let (Some(mean_x), Some(mean_y)) = (
+        safe_div(pairs.iter().map(|(x, _)| x).sum::<f64>(), count),
+        safe_div(pairs.iter().map(|(_, y)| y).sum::<f64>(), count),
+    ) else {
+        return Ok(null);
+    };
+    let sxx: f64 = pairs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let syy: f64 = pairs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    let sxy: f64 = pairs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let Some(slope) = safe_div(sxy, sxx) else {
+        return Ok(null);
+    };
+
+    let intercept = mean_y - slope * mean_x;
+    let r_squared = safe_div(sxy * sxy, sxx * syy);
+
+    Ok(json!({
+        "slope": slope,
+        "intercept": intercept,
+        "r_squared": r_squared,
+        "n": n,
+    }))
+}
+
+/// `(x, y)` pairs from records where both fields are numeric.
+fn paired_values(data: &[Value], x: &str, y: &str) -> Vec<(f64, f64)> {
+    data.iter()
+        .filter_map(|item| Some((numeric_field(item, x)?, numeric_field(item, y)?)))
+        .collect()
+}
+
+/// Count occurrences of each distinct value of a categorical `field`.
+///
+/// Records missing the field are counted under null. By default results are
+/// sorted by count, descending; `"sort": "value"` sorts by the value instead.
+/// An optional `order` array ranks category values: it breaks ties in count
+/// order and is the primary ordering for `"sort": "value"`. Remaining ties
+/// fall back to first occurrence, so the output is deterministic.
+///
+/// `"top": N` keeps the first N values in that order and folds the rest
+/// into a single `"(other)"` entry.
+fn value_counts(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for value_counts operation".into(),
+        ))?;
+    let sort = input
+        .get("sort")
+        .and_then(|v| v.as_str())
+        .unwrap_or("count");
+    if sort != "count" && sort != "value" {
+        return Err(format!("Unknown sort: {sort}. Use: count, value").into());
+    }
+    let order: &[Value] = match input.get("order") {
+        Some(v) => v.as_array().ok_or(TransformError::WrongType(
+            "'order' must be an array of values".into(),
+        ))?,
+        None => &[],
+    };
+
+    let mut counts: Vec<(Value, usize)> = group_records(data, field)
+        .into_iter()
+        .map(|(value, items)| (value, items.len()))
+        .collect();
+
+    // Values listed in `order` rank by position; unlisted values rank after them
+    let rank = |v: &Value| order.iter().position(|o| o == v).unwrap_or(order.len());
+    counts.sort_by(|(va, ca), (vb, cb)| match sort {
+        "value" if !order.is_empty() => rank(va).cmp(&rank(vb)).then(compare_values(va, vb)),
+        "value" => compare_values(va, vb),
+        _ => cb.cmp(ca).then(rank(va).cmp(&rank(vb))),
+    });
+
+    let top = match input.get("top") {
+        None => None,
+        Some(v) => Some(v.as_u64().ok_or("'top' must be a non-negative integer")? as usize),
+    };
+    let distinct = counts.len();
+    let other: usize = match top {
+        Some(n) if n < counts.len() => counts.drain(n..).map(|(_, count)| count).sum(),
+        _ => 0,
+    };
+
+    let mut values: Vec<Value> = counts
+        .iter()
+        .map(|(value, count)| json!({"value": value, "count": count}))
+        .collect();
+    if other > 0 {
+        values.push(json!({"value": "(other)", "count": other}));
+    }
+
+    Ok(json!({
+        "values": values,
+        "distinct": distinct,
+        "total": data.len(),
+    }))
+}
+
+/// Total order over JSON values: null < booleans < numbers < strings < others.
+fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
+    fn type_rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+    match (a, b) {
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(std::cmp::Ordering::Equal),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        _ => type_rank(a)
+            .cmp(&type_rank(b))
+            .then_with(|| canonical_json(a).cmp(&canonical_json(b))),
+    }
+}
+
+/// Return a window of `data` starting at `offset` (default 0) with at most
+/// `limit` records (default: the rest).
+///
+/// A negative `offset` counts from the end only with `"from_end": true`;
+/// otherwise it yields an empty window, as does an offset past the end.
+fn slice_data(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+
+    let (offset, limit) = page_params(input)?;
+    let from_end = input
+        .get("from_end")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let range = window(data.len(), offset, limit, from_end);
+    let start = range.start;
+    let has_more = range.end < data.len();
+    let page = &data[range];
+
+    Ok(json!({
+        "data": page,
+        "count": page.len(),
+        "total": data.len(),
+        "offset": start,
+        "limit": limit,
+        "has_more": has_more,
+    }))
+}
+
+/// The `offset` (default 0) and optional `limit` pagination parameters.
+fn page_params(input: &Value) -> Result<(i64, Option<usize>), TransformError> {
+    let offset = match input.get("offset") {
+        Some(v) => v.as_i64().ok_or(TransformError::WrongType(
+            "'offset' must be an integer".into(),
+        ))?,
+        None => 0,
+    };
+    let limit = match input.get("limit") {
+        Some(v) => Some(v.as_u64().ok_or(TransformError::WrongType(
+            "'limit' must be a non-negative integer".into(),
+        ))? as usize),
+        None => None,
+    };
+    Ok((offset, limit))
+}
+
+/// Apply `offset`/`limit` to `result["data"]` when either is given, adding
+/// the pre-page `total` and `has_more` like slice. Offsets that are
+/// negative or past the end give an empty page.
+fn paginate(input: &Value, result: &mut Value) -> Result<(), TransformError> {
+    if input.get("offset").is_none() && input.get("limit").is_none() {
+        return Ok(());
+    }
+    let (offset, limit) = page_params(input)?;
+    let Some(rows) = result.get_mut("data").and_then(|v| v.as_array_mut()) else {
+        return Ok(());
+    };
+    let total = rows.len();
+    let range = window(total, offset, limit, false);
+    let (start, has_more) = (range.start, range.end < total);
+    let page: Vec<Value> = rows.drain(range).collect();
+    result["count"] = json!(page.len());
+    result["data"] = json!(page);
+    result["total"] = json!(total);
+    result["offset"] = json!(start);
+    result["limit"] = json!(limit);
+    result["has_more"] = json!(has_more);
+    Ok(())
+}
+
+/// Index range for an `offset`/`limit` window over `len` items, clamped so it
+/// never panics. Negative offsets count from the end when `from_end` is set
+/// and produce an empty range otherwise.
+fn window(len: usize, offset: i64, limit: Option<usize>, from_end: bool) -> std::ops::Range<usize> {
+    let start = match (offset < 0, from_end) {
+        (false, _) => (offset as usize).min(len),
+        (true, true) => len.saturating_sub(offset.unsigned_abs() as usize),
+        (true, false) => len,
+    };
+    let end = match limit {
+        Some(limit) => start.saturating_add(limit).min(len),
+        None => len,
+    };
+    start..end
+}
+
+/// Turn column-oriented `data` (`{"names": [...], "ages": [...]}`) into an
+/// array of records, one per index.
+///
+/// Columns of different lengths are truncated to the shortest, or padded with
+/// null to the longest when `"pad": true`.
+fn zip_columns(input: &Value) -> Result<Value, TransformError> {
+    let columns =
+        input
+            .get("data")
+            .and_then(|v| v.as_object())
+            .ok_or(TransformError::WrongType(
+                "'data' must be an object of named arrays".into(),
+            ))?;
+    let pad = input.get("pad").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let columns: Vec<(&String, &Vec<Value>)> = columns
+        .iter()
+        .map(|(name, col)| {
+            col.as_array()
+                .map(|items| (name, items))
+                .ok_or_else(|| format!("'data.{name}' must be an array"))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let lengths = columns.iter().map(|(_, col)| col.len());
+    let len = if pad { lengths.max() } else { lengths.min() }.unwrap_or(0);
+
+    let rows: Vec<Value> = (0..len)
+        .map(|i| {
+            Value::Object(
+                columns
+                    .iter()
+                    .map(|(name, col)| {
+                        ((*name).clone(), col.get(i).cloned().unwrap_or(Value::Null))
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Infer a JSON Schema describing `data`.
+///
+/// Arrays are described by a single `items` schema merged across all elements;
+/// object properties present in every sample are listed as `required`.
+/// Recursion stops at `max_depth` (default 10) and objects with more than
+/// `max_properties` keys (default 100) collapse to a bare `object` type; the
+/// paths where that happened are listed under `truncated`.
+fn infer_schema(input: &Value) -> Result<Value, TransformError> {
+    let data = input.get("data").ok_or(TransformError::MissingField(
+        "'data' is required for infer_schema".into(),
+    ))?;
+    let limits = SchemaLimits {
+        max_depth: input
+            .get("max_depth")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(10) as usize,
+        max_properties: input
+            .get("max_properties")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(100) as usize,
+    };
+
+    let mut truncated: Vec<String> = Vec::new();
+    let schema = infer_from_samples(&[data], 0, "", &limits, &mut truncated);
+
+    Ok(json!({
+        "schema": schema,
+        "truncated": truncated,
+    }))
+}
+
+struct SchemaLimits {
+    max_depth: usize,
+    max_properties: usize,
+}
+
+fn infer_from_samples(
+    samples: &[&Value],
+    depth: usize,
+    path: &str,
+    limits: &SchemaLimits,
+    truncated: &mut Vec<String>,
+) -> Value {
+    let mut types: Vec<&str> = Vec::new();
+    for sample in samples {
+        let t = match sample {
+            Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+            other => type_name(other),
+        };
+        if !types.contains(&t) {
+            types.push(t);
+        }
+    }
+    // Integers and floats at the same position are just numbers
+    if types.contains(&"integer") && types.contains(&"number") {
+        types.retain(|t| *t != "integer");
+    }
+
+    let mut schema = serde_json::Map::new();
+    schema.insert(
+        "type".into(),
+        match types.as_slice() {
+            [single] => json!(single),
+            many => json!(many),
+        },
+    );
+
+    let objects: Vec<&serde_json::Map<String, Value>> =
+        samples.iter().filter_map(|s| s.as_object()).collect();
+    if !objects.is_empty() {
+        let mut keys: Vec<&String> = Vec::new();
+        for obj in &objects {
+            for key in obj.keys() {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        if depth >= limits.max_depth || keys.len() > limits.max_properties {
+            truncated.push(if path.is_empty() {
+                "/".into()
+            } else {
+                path.to_string()
+            });
+        } else {
+            let mut properties = serde_json::Map::new();
+            let mut required = Vec::new();
+            for key in keys {
+                let values: Vec<&Value> = objects.iter().filter_map(|o| o.get(key)).collect();
+                if values.len() == objects.len() {
+                    required.push(key.clone());
+                }
+                let child_path = format!("{path}/{key}");
+                properties.insert(
+                    key.clone(),
+                    infer_from_samples(&values, depth + 1, &child_path, limits, truncated),
+                );
+            }
+            schema.insert("properties".into(), Value::Object(properties));
+            schema.insert("required".into(), json!(required));
+        }
+    }
+
+    let items: Vec<&Value> = samples
+        .iter()
+        .filter_map(|s| s.as_array())
+        .flatten()
+        .collect();
+    if !items.is_empty() {
+        if depth >= limits.max_depth {
+            truncated.push(format!("{path}/*"));
+        } else {
+            let child_path = format!("{path}/*");
+            schema.insert(
+                "items".into(),
+                infer_from_samples(&items, depth + 1, &child_path, limits, truncated),
+            );
+        }
+    }
+
+    Value::Object(schema)
+}
+
+/// Remove the named `fields` from every record, leaving other keys intact.
+///
+/// Field names may use a trailing `*` to match a prefix (`internal_*`) or a
+/// leading `*` to match a suffix (`*_token`). Non-object items pass through.
+fn omit_fields(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let patterns: Vec<&str> = input
+        .get("fields")
+        .and_then(|v| v.as_array())
+        .ok_or(TransformError::WrongType(
+            "'fields' must be an array of field names".into(),
+        ))?
+        .iter()
+        .filter_map(|f| f.as_str())
+        .collect();
+
+    let mut removed = 0;
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| match item {
+            Value::Object(obj) => {
+                let kept: serde_json::Map<String, Value> = obj
+                    .iter()
+                    .filter(|(key, _)| !patterns.iter().any(|p| field_pattern_matches(p, key)))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                removed += obj.len() - kept.len();
+                Value::Object(kept)
+            }
+            other => other.clone(),
+        })
+        .collect();
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "removed_fields": removed,
+    }))
+}
+
+/// Keep only the named `fields` of each record, in the order given; missing
+/// fields are simply left out. Entries may be wildcards as in omit
+/// (`prefix*`, `*suffix`). A dotted path is written as a flat `"user.name"`
+/// key, or nested as `{"user": {"name": ...}}` with `"flatten": false`.
+fn select_fields(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let fields: Vec<&str> = input
+        .get("fields")
+        .and_then(|v| v.as_array())
+        .ok_or(TransformError::WrongType(
+            "'fields' must be an array of field names".into(),
+        ))?
+        .iter()
+        .filter_map(|f| f.as_str())
+        .collect();
+    let flatten = input
+        .get("flatten")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let Some(obj) = item.as_object() else {
+                return item.clone();
+            };
+            let mut selected = serde_json::Map::new();
+            for field in &fields {
+                if field.contains('*') {
+                    for (key, value) in obj.iter().filter(|(k, _)| field_pattern_matches(field, k))
+                    {
+                        selected.insert(key.clone(), value.clone());
+                    }
+                    continue;
+                }
+                let Some(value) = field_value(item, field) else {
+                    continue;
+                };
+                if flatten || obj.contains_key(*field) {
+                    selected.insert(field.to_string(), value.clone());
+                } else {
+                    insert_path(&mut selected, field, value.clone());
+                }
+            }
+            Value::Object(selected)
+        })
+        .collect();
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Rename keys in every record using `map` (`{"old": "new"}`), keeping key
+/// order; unmapped keys pass through. Renaming onto a key the record already
+/// has (and that isn't itself being renamed away) is an error rather than
+/// a silent overwrite.
+fn rename_fields(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let map = input
+        .get("map")
+        .and_then(|v| v.as_object())
+        .ok_or(TransformError::WrongType(
+            "'map' must be an object of old → new field names".into(),
+        ))?;
+    let renames: Vec<(&str, &str)> = map
+        .iter()
+        .map(|(from, to)| {
+            to.as_str()
+                .map(|to| (from.as_str(), to))
+                .ok_or(format!("'map.{from}' must be a field name"))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut renamed = 0;
+    let mut rows: Vec<Value> = Vec::with_capacity(data.len());
+    for (i, item) in data.iter().enumerate() {
+        let Some(obj) = item.as_object() else {
+            rows.push(item.clone());
+            continue;
+        };
+        let mut record = serde_json::Map::new();
+        for (key, value) in obj {
+            let new_key = renames
+                .iter()
+                .find(|(from, _)| from == key)
+                .map_or(key.as_str(), |(_, to)| *to);
+            if record.contains_key(new_key) {
+                return Err(format!(
+                    "Record {i}: renaming '{key}' to '{new_key}' would overwrite an existing field"
+                )
+                .into());
+            }
+            if new_key != key {
+                renamed += 1;
+            }
+            record.insert(new_key.to_string(), value.clone());
+        }
+        rows.push(Value::Object(record));
+    }
+
+    Ok(json!({"data": rows, "count": rows.len(), "renamed": renamed}))
+}
+
+/// Insert `value` at a dotted `path`, creating intermediate objects.
+fn insert_path(map: &mut serde_json::Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            map.insert(path.to_string(), value);
+        }
+        Some((head, rest)) => {
+            let child = map
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(serde_json::Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(serde_json::Map::new());
+            }
+            if let Some(child) = child.as_object_mut() {
+                insert_path(child, rest, value);
+            }
+        }
+    }
+}
+
+/// Compute derived fields: each of `rules` is `{"target": name, "expr": ...}`
+/// and writes the expression's value into `target`, in order, so later rules
+/// can use earlier targets. See [`Expr`] for the expression syntax.
+///
+/// Division by zero gives null. A rule referencing a missing or non-numeric
+/// field is skipped for that record, or writes null with
+/// `"on_missing": "null"`; `skipped` counts skipped rule applications.
+fn map_fields(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let rules: Vec<(&str, Expr)> = array_field(input, "rules")?
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            let target = rule.get("target").and_then(|v| v.as_str()).ok_or_else(|| {
+                TransformError::MissingField(format!("'rules[{i}].target' is required"))
+            })?;
+            let expr = rule.get("expr").ok_or_else(|| {
+                TransformError::MissingField(format!("'rules[{i}].expr' is required"))
+            })?;
+            let expr = Expr::parse(expr).map_err(|e| format!("'rules[{i}].expr': {e}"))?;
+            Ok((target, expr))
+        })
+        .collect::<Result<_, TransformError>>()?;
+    let write_null = match input.get("on_missing").and_then(|v| v.as_str()) {
+        None | Some("skip") => false,
+        Some("null") => true,
+        Some(other) => {
+            return Err(format!("Unknown on_missing mode: {other}. Use: skip, null").into());
+        }
+    };
+
+    let mut skipped = 0;
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let mut record = item.clone();
+            for (target, expr) in &rules {
+                let value = match expr.eval(&record) {
+                    Ok(value) => value,
+                    Err(_) if write_null => None,
+                    Err(_) => {
+                        skipped += 1;
+                        continue;
+                    }
+                };
+                if let Some(obj) = record.as_object_mut() {
+                    obj.insert(target.to_string(), json!(value));
+                }
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({"data": rows, "count": rows.len(), "skipped": skipped}))
+}
+
+/// Arithmetic expression for the map operation. In JSON: a number is a
+/// literal, a string or `{"field": name}` reads a field (dot paths allowed),
+/// `{"value": n}` is a literal, and `{"op": "+", "args": [...]}` applies
+/// `+`, `-`, `*` or `/` left to right (a lone `-` argument is negated).
+enum Expr {
+    Literal(f64),
+    Field(String),
+    Apply(char, Vec<Expr>),
+}
+
+impl Expr {
+    fn parse(expr: &Value) -> Result<Self, String> {
+        match expr {
+            Value::Number(n) => Ok(Expr::Literal(n.as_f64().unwrap_or_default())),
+            Value::String(field) => Ok(Expr::Field(field.clone())),
+            Value::Object(obj) => {
+                if let Some(field) = obj.get("field") {
+                    let field = field.as_str().ok_or("'field' must be a field name")?;
+                    return Ok(Expr::Field(field.to_string()));
+                }
+                if let Some(value) = obj.get("value") {
+                    let value = value.as_f64().ok_or("'value' must be a number")?;
+                    return Ok(Expr::Literal(value));
+                }
+                let op = match obj.get("op").and_then(|v| v.as_str()) {
+                    Some("+") => '+',
+                    Some("-") => '-',
+                    Some("*") => '*',
+                    Some("/") => '/',
+                    Some(other) => return Err(format!("Unknown op: {other}. Use: +, -, *, /")),
+                    None => return Err("expected a number, field, {value} or {op, args}".into()),
+                };
+                let args = obj
+                    .get("args")
+                    .and_then(|v| v.as_array())
+                    .filter(|args| !args.is_empty())
+                    .ok_or("'args' must be a non-empty array")?
+                    .iter()
+                    .map(Expr::parse)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Expr::Apply(op, args))
+            }
+            other => Err(format!("unsupported expression: {other}")),
+        }
+    }
+
+    /// The value for `record`: `Ok(None)` is null (division by zero
+    /// somewhere), `Err` names a missing or non-numeric field.
+    fn eval<'e>(&'e self, record: &Value) -> Result<Option<f64>, &'e str> {
+        match self {
+            Expr::Literal(n) => Ok(Some(*n)),
+            Expr::Field(field) => numeric_field(record, field).map(Some).ok_or(field.as_str()),
+            Expr::Apply(op, args) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    match arg.eval(record)? {
+                        Some(v) => values.push(v),
+                        None => return Ok(None),
+                    }
+                }
+                if let ('-', [only]) = (op, values.as_slice()) {
+                    return Ok(Some(-only));
+                }
+                let mut rest = values.into_iter();
+                let first = rest.next().unwrap_or_default();
+                let mut acc = Some(first);
+                for v in rest {
+                    acc = acc.and_then(|a| match op {
+                        '+' => Some(a + v),
+                        '-' => Some(a - v),
+                        '*' => Some(a * v),
+                        _ => safe_div(a, v),
+                    });
+                }
+                Ok(acc.filter(|v| v.is_finite()))
+            }
+        }
+    }
+}
+
+/// Replace missing or null fields with defaults from the `fill` map, e.g.
+/// `{"score": 0, "name": "unknown"}`. A fill of "mean" or "median" is
+/// computed from the field's present numeric values (and leaves the field
+/// alone when there are none). `"fill": "mean"` or `"median"` on its own
+/// applies to every field listed in `fields`.
+///
+/// `filled` counts the cells filled per field.
+fn fill_missing(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let rules: Vec<(&str, &Value)> = match input.get("fill") {
+        Some(Value::Object(map)) => map.iter().map(|(k, v)| (k.as_str(), v)).collect(),
+        Some(strategy @ Value::String(_)) => input
+            .get("fields")
+            .and_then(|v| v.as_array())
+            .ok_or(TransformError::MissingField(
+                "'fields' is required when 'fill' is a strategy".into(),
+            ))?
+            .iter()
+            .map(|f| {
+                f.as_str()
+                    .map(|f| (f, strategy))
+                    .ok_or(TransformError::WrongType(
+                        "'fields' must be an array of field names".into(),
+                    ))
+            })
+            .collect::<Result<_, _>>()?,
+        None => {
+            return Err(TransformError::MissingField(
+                "'fill' is required for fillna operation".into(),
+            ))
+        }
+        Some(_) => {
+            return Err(TransformError::WrongType(
+                "'fill' must be an object mapping fields to values, or \"mean\"/\"median\"".into(),
+            ))
+        }
+    };
+
+    // Resolve each rule to the value it fills with (None: nothing to fill)
+    let fills: Vec<(&str, Option<Value>)> = rules
+        .into_iter()
+        .map(|(field, rule)| {
+            let computed = |f: fn(&[f64]) -> Option<f64>| f(&field_values(data.iter(), field));
+            let value = match rule.as_str() {
+                Some("mean") => {
+                    computed(|v| safe_div(v.iter().sum(), v.len() as f64)).map(|m| json!(m))
+                }
+                Some("median") => computed(median).map(|m| json!(m)),
+                _ => Some(rule.clone()),
+            };
+            (field, value)
+        })
+        .collect();
+
+    let mut filled: Vec<usize> = vec![0; fills.len()];
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                for (i, (field, value)) in fills.iter().enumerate() {
+                    let Some(value) = value else { continue };
+                    if obj.get(*field).is_none_or(|v| v.is_null()) {
+                        obj.insert(field.to_string(), value.clone());
+                        filled[i] += 1;
+                    }
+                }
+            }
+            record
+        })
+        .collect();
+
+    let filled: serde_json::Map<String, Value> = fills
+        .iter()
+        .zip(filled)
+        .map(|((field, _), n)| (field.to_string(), json!(n)))
+        .collect();
+    Ok(json!({"data": rows, "count": rows.len(), "filled": filled}))
+}
+
+/// Flatten nested objects in each record into dot-keyed fields
+/// (`{"a": {"b": 1}}` → `{"a.b": 1}`), up to `depth` levels of nesting
+/// (default 10); objects below that are kept as values. Arrays are left
+/// as-is unless named by `explode`, which emits one row per element (a row
+/// with null for an empty array) before flattening.
+fn flatten_records(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let depth = match input.get("depth") {
+        None => 10,
+        Some(v) => v.as_u64().ok_or("'depth' must be a non-negative integer")? as usize,
+    };
+    let explode = match input.get("explode") {
+        None => None,
+        Some(v) => Some(v.as_str().ok_or(TransformError::WrongType(
+            "'explode' must be a field name".into(),
+        ))?),
+    };
+
+    let mut rows: Vec<Value> = Vec::new();
+    for item in data {
+        let expanded: Vec<Value> = match (explode, item.as_object()) {
+            (Some(field), Some(obj)) => match obj.get(field) {
+                Some(Value::Array(elements)) if elements.is_empty() => {
+                    let mut row = obj.clone();
+                    row.insert(field.to_string(), Value::Null);
+                    vec![Value::Object(row)]
+                }
+                Some(Value::Array(elements)) => elements
+                    .iter()
+                    .map(|element| {
+                        let mut row = obj.clone();
+                        row.insert(field.to_string(), element.clone());
+                        Value::Object(row)
+                    })
+                    .collect(),
+                _ => vec![item.clone()],
+            },
+            _ => vec![item.clone()],
+        };
+        for row in expanded {
+            match row {
+                Value::Object(obj) => {
+                    let mut flat = serde_json::Map::new();
+                    flatten_into(&mut flat, "", obj, depth);
+                    rows.push(Value::Object(flat));
+                }
+                other => rows.push(other),
+            }
+        }
+    }
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Copy `obj` into `flat` with keys prefixed by `prefix`, descending into
+/// nested (non-empty) objects while `depth` allows.
+fn flatten_into(
+    flat: &mut serde_json::Map<String, Value>,
+    prefix: &str,
+    obj: serde_json::Map<String, Value>,
+    depth: usize,
+) {
+    for (key, value) in obj {
+        let path = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Object(child) if depth > 0 && !child.is_empty() => {
+                flatten_into(flat, &path, child, depth - 1);
+            }
+            other => {
+                flat.insert(path, other);
+            }
+        }
+    }
+}
+
+/// Match a key against a field pattern: exact, `prefix*`, or `*suffix`.
+fn field_pattern_matches(pattern: &str, key: &str) -> bool {
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        key.starts_with(prefix)
+    } else if let Some(suffix) = pattern.strip_prefix('*') {
+        key.ends_with(suffix)
+    } else {
+        pattern == key
+    }
+}
+
+/// Express each record's `field` relative to its `group_by` group.
+///
+/// Methods: `deviation` (value − group mean), `pct_of_group` (value as a
+/// percentage of the group total) and `zscore_within_group` (population
+/// z-score within the group). The result goes to `output` (default
+/// `normalized`); records without a numeric value, or whose group makes the
+/// result undefined (zero total or zero spread), get null. Input order is kept.
+fn group_normalize(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let group_by =
+        input
+            .get("group_by")
+            .and_then(|v| v.as_str())
+            .ok_or(TransformError::MissingField(
+                "'group_by' is required for group_normalize operation".into(),
+            ))?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for group_normalize operation".into(),
+        ))?;
+    let method = input
+        .get("method")
+        .and_then(|v| v.as_str())
+        .unwrap_or("deviation");
+    if !matches!(method, "deviation" | "pct_of_group" | "zscore_within_group") {
+        return Err(format!(
+            "Unknown method: {method}. Use: deviation, pct_of_group, zscore_within_group"
+        )
+        .into());
+    }
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("normalized");
+
+    // First pass: (sum, mean, std_dev) per group
+    let mut group_stats: std::collections::HashMap<String, (f64, Option<f64>, Option<f64>)> =
+        std::collections::HashMap::new();
+    for (key, items) in group_records(data, group_by) {
+        let values: Vec<f64> = items
+            .iter()
+            .filter_map(|i| numeric_field(i, field))
+            .collect();
+        let count = values.len() as f64;
+        let sum: f64 = values.iter().sum();
+        let mean = safe_div(sum, count);
+        let std_dev = mean.and_then(|m| {
+            safe_div(values.iter().map(|v| (v - m).powi(2)).sum::<f64>(), count).map(f64::sqrt)
+        });
+        group_stats.insert(canonical_json(&key), (sum, mean, std_dev));
+    }
+
+    // Second pass: apply the group statistic to each record
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let key = canonical_json(field_value(item, group_by).unwrap_or(&Value::Null));
+            let &(sum, mean, std_dev) = group_stats.get(&key).ok_or_else(|| {
+                TransformError::InvalidValue(format!("No group statistics for key {key}"))
+            })?;
+            let normalized = numeric_field(item, field).and_then(|v| match method {
+                "pct_of_group" => safe_div(v, sum).map(|r| r * 100.0),
+                "zscore_within_group" => safe_div(v - mean?, std_dev?),
+                _ => Some(v - mean?),
+            });
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(normalized));
+            }
+            Ok(record)
+        })
+        .collect::<Result<_, TransformError>>()?;
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Collapse consecutive equal values of `field` into `{value, count, start_index}`
+/// runs, in input order.
+///
+/// Without `field`, the items themselves are compared. Missing values are
+/// treated as null. `longest_run` is the first run of maximal length.
+fn run_length_encode(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input.get("field").and_then(|v| v.as_str());
+
+    let mut runs: Vec<(&Value, usize, usize)> = Vec::new();
+    for (i, item) in data.iter().enumerate() {
+        let value = match field {
+            Some(f) => field_value(item, f).unwrap_or(&Value::Null),
+            None => item,
+        };
+        match runs.last_mut() {
+            Some((last, count, _)) if *last == value => *count += 1,
+            _ => runs.push((value, 1, i)),
+        }
+    }
+
+    let longest = runs
+        .iter()
+        .fold(
+            None,
+            |best: Option<&(&Value, usize, usize)>, run| match best {
+                Some(b) if b.1 >= run.1 => Some(b),
+                _ => Some(run),
+            },
+        )
+        .map(|(value, count, start)| json!({"value": value, "count": count, "start_index": start}));
+
+    let runs: Vec<Value> = runs
+        .iter()
+        .map(|(value, count, start)| json!({"value": value, "count": count, "start_index": start}))
+        .collect();
+
+    Ok(json!({
+        "runs": runs,
+        "run_count": runs.len(),
+        "longest_run": longest,
+    }))
+}
+
+/// Extract part of a string `field` into an `output` field using a regex.
+///
+/// The first capture group is written when the pattern has one, otherwise the
+/// whole match. Records that don't match, or whose field isn't a string, get
+/// null.
+fn extract_pattern(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for extract operation".into(),
+        ))?;
+    let pattern =
+        input
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or(TransformError::MissingField(
+                "'pattern' is required for extract operation".into(),
+            ))?;
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("extracted");
+
+    let regex = compile_pattern(pattern)?;
+    let has_group = regex.captures_len() > 1;
+
+    let mut matched = 0;
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let extracted = field_value(item, field)
+                .and_then(|v| v.as_str())
+                .and_then(|text| regex.captures(text))
+                .and_then(|caps| caps.get(if has_group { 1 } else { 0 }))
+                .map(|m| m.as_str().to_string());
+            if extracted.is_some() {
+                matched += 1;
+            }
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(extracted));
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "matched": matched,
+    }))
+}
+
+fn compile_pattern(pattern: &str) -> Result<Regex, TransformError> {
+    Regex::new(pattern)
+        .map_err(|e| TransformError::InvalidValue(format!("Invalid pattern '{pattern}': {e}")))
+}
+
+/// Most bins a histogram or sparkline may ask for.
+const MAX_BUCKETS: u64 = 10_000;
+
+/// Equal-width histogram of a numeric `field` over `bins` bins (default 10,
+/// at most `MAX_BUCKETS`).
+///
+/// The range defaults to the data's min–max; the last bin includes its upper
+/// edge. `normalize` is `count` (default), `frequency` (counts sum to 1) or
+/// `density` (area sums to 1); the latter two add a value of that name to each
+/// bin, and `density` also reports `bin_width`.
+fn histogram(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+    let bins = input.get("bins").and_then(|v| v.as_u64()).unwrap_or(10);
+    if !(1..=MAX_BUCKETS).contains(&bins) {
+        return Err(format!("'bins' must be between 1 and {MAX_BUCKETS}").into());
+    }
+    let bins = bins as usize;
+    let normalize = input
+        .get("normalize")
+        .and_then(|v| v.as_str())
+        .unwrap_or("count");
+    if !matches!(normalize, "count" | "frequency" | "density") {
+        return Err(
+            format!("Unknown normalize mode: {normalize}. Use: count, frequency, density").into(),
+        );
+    }
+
+    let values = field_values(data.iter(), field);
+    let min = input
+        .get("min")
+        .and_then(|v| v.as_f64())
+        .or_else(|| values.iter().cloned().reduce(f64::min));
+    let max = input
+        .get("max")
+        .and_then(|v| v.as_f64())
+        .or_else(|| values.iter().cloned().reduce(f64::max));
+    let (Some(min), Some(max)) = (min, max) else {
+        return Ok(json!({"bins": [], "count": 0}));
+    };
+    if min > max {
+        return Err(format!("'min' ({min}) must not be greater than 'max' ({max})").into());
+    }
+
+    let bin_width = bucket_width(min, max, bins)?;
+    let counts = bucket_counts(&values, bins, min, max, bin_width);
+    let total: usize = counts.iter().sum();
+
+    let bins_out: Vec<Value> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let mut bin = json!({
+                "start": min + bin_width * i as f64,
+                "end": if i + 1 == bins { max } else { min + bin_width * (i + 1) as f64 },
+                "count": count,
+            });
+            match normalize {
+                "frequency" => bin["frequency"] = json!(safe_div(*count as f64, total as f64)),
+                "density" => {
+                    bin["density"] = json!(safe_div(*count as f64, total as f64 * bin_width))
+                }
+                _ => {}
+            }
+            bin
+        })
+        .collect();
+
+    let mut result = json!({"bins": bins_out, "count": total});
+    if normalize == "density" {
+        result["bin_width"] = json!(bin_width);
+    }
+    Ok(result)
+}
+
+/// Width of each of `buckets` equal-width buckets over `[min, max]`, or an
+/// error when the range is too wide to represent.
+fn bucket_width(min: f64, max: f64, buckets: usize) -> Result<f64, TransformError> {
+    safe_div(max - min, buckets as f64).ok_or_else(|| {
+        TransformError::InvalidValue(format!("Range {min}–{max} is too wide to bucket"))
+    })
+}
+
+/// Count `values` into `buckets` buckets of `width` starting at `min`.
+/// Values outside `[min, max]` are ignored; `max` lands in the last bucket.
+fn bucket_counts(values: &[f64], buckets: usize, min: f64, max: f64, width: f64) -> Vec<usize> {
+    let mut counts = vec![0; buckets];
+    for &v in values {
+        if v < min || v > max {
+            continue;
+        }
+        let idx = safe_div(v - min, width)
+            .map(|i| (i as usize).min(buckets - 1))
+            .unwrap_or(0);
+        counts[idx] += 1;
+    }
+    counts
+}
+
+/// Flat bucket counts of `field` over its own min–max range, for inline
+/// sparklines: `{counts, min, max}` without the per-bin objects of histogram.
+fn sparkline(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+    let buckets = input.get("buckets").and_then(|v| v.as_u64()).unwrap_or(10);
+    if !(1..=MAX_BUCKETS).contains(&buckets) {
+        return Err(format!("'buckets' must be between 1 and {MAX_BUCKETS}").into());
+    }
+    let buckets = buckets as usize;
+
+    let values = field_values(data.iter(), field);
+    let (Some(min), Some(max)) = (
+        values.iter().cloned().reduce(f64::min),
+        values.iter().cloned().reduce(f64::max),
+    ) else {
+        return Ok(json!({"counts": vec![0; buckets], "min": null, "max": null}));
+    };
+
+    let width = bucket_width(min, max, buckets)?;
+    Ok(json!({
+        "counts": bucket_counts(&values, buckets, min, max, width),
+        "min": min,
+        "max": max,
+    }))
+}
+
+/// Write a weighted composite score per record: `sum(weight * field)`.
+///
+/// `weights` maps field names to weights. With `"normalize": true` each field
+/// is min-max scaled over `data` first so weights are comparable. A missing or
+/// non-numeric field contributes zero by default; with `"missing": "exclude"`
+/// the record's score is null instead.
+fn composite_score(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let weights: Vec<(&str, f64)> = input
+        .get("weights")
+        .and_then(|v| v.as_object())
+        .ok_or(TransformError::WrongType(
+            "'weights' must be an object mapping fields to numbers".into(),
+        ))?
+        .iter()
+        .map(|(field, w)| {
+            w.as_f64()
+                .map(|w| (field.as_str(), w))
+                .ok_or_else(|| format!("'weights.{field}' must be a number"))
+        })
+        .collect::<Result<_, _>>()?;
+    let normalize = input
+        .get("normalize")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let exclude_missing = match input.get("missing").and_then(|v| v.as_str()) {
+        None | Some("zero") => false,
+        Some("exclude") => true,
+        Some(other) => {
+            return Err(format!("Unknown missing mode: {other}. Use: zero, exclude").into())
+        }
+    };
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("score");
+
+    // Per-field (min, range) for min-max scaling
+    let bounds: Vec<(f64, f64)> = weights
+        .iter()
+        .map(|(field, _)| {
+            let values: Vec<f64> = data
+                .iter()
+                .filter_map(|i| numeric_field(i, field))
+                .collect();
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            (min, max - min)
+        })
+        .collect();
+
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let mut total = Some(0.0);
+            for ((field, weight), (min, range)) in weights.iter().zip(&bounds) {
+                let value = match numeric_field(item, field) {
+                    Some(v) if normalize => safe_div(v - min, *range).unwrap_or(0.0),
+                    Some(v) => v,
+                    None if exclude_missing => {
+                        total = None;
+                        break;
+                    }
+                    None => 0.0,
+                };
+                total = total.map(|t| t + weight * value);
+            }
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(total));
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Add a hex hash of each record (or just its `fields`) for change detection.
+///
+/// The hash is FNV-1a over the canonical JSON of the selected values, so it
+/// ignores key order and is stable across runs — but it is not collision
+/// resistant and must not be used for security.
+fn checksum(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let fields: Option<Vec<&str>> = input
+        .get("fields")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|f| f.as_str()).collect());
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("checksum");
+
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let hash = format!(
+                "{:016x}",
+                fnv1a_64(record_key(item, fields.as_deref()).as_bytes())
+            );
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(hash));
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Hex FNV-1a hash of the canonical input, ignoring the `cache_key` flag itself.
+fn cache_key(input: &Value) -> String {
+    let mut normalized = input.clone();
+    if let Some(obj) = normalized.as_object_mut() {
+        obj.remove("cache_key");
+    }
+    format!("{:016x}", fnv1a_64(canonical_json(&normalized).as_bytes()))
+}
+
+/// Serialize with object keys sorted at every level, so equal values always
+/// produce identical text regardless of key insertion order.
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .iter()
+                .map(|k| {
+                    format!(
+                        "{}:{}",
+                        Value::String((*k).clone()),
+                        canonical_json(&map[*k])
+                    )
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// 64-bit FNV-1a — fast and stable, but not suitable for security purposes.
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Fill missing or null values of `field` from surrounding known values, in
+/// input order.
+///
+/// Methods: `linear` (default; straight line between the nearest known values
+/// on either side, by position), `forward` (carry the previous value) and
+/// `backward` (take the next value). Gaps with no value on the needed side stay
+/// null unless `"fill_edges": true`, which uses the nearest known value.
+/// Non-numeric values are treated as gaps too.
+fn interpolate(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for interpolate operation".into(),
+        ))?;
+    let method = input
+        .get("method")
+        .and_then(|v| v.as_str())
+        .unwrap_or("linear");
+    if !matches!(method, "linear" | "forward" | "backward") {
+        return Err(format!("Unknown method: {method}. Use: linear, forward, backward").into());
+    }
+    let fill_edges = input
+        .get("fill_edges")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let values: Vec<Option<f64>> = data.iter().map(|item| numeric_field(item, field)).collect();
+    let known = |i: usize| values[i].map(|v| (i, v));
+
+    let mut filled = 0;
+    let rows: Vec<Value> = data
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            if values[i].is_some() {
+                return item.clone();
+            }
+            let prev = (0..i).rev().find_map(known);
+            let next = (i + 1..values.len()).find_map(known);
+            let estimate = match (method, prev, next) {
+                ("linear", Some((j, a)), Some((k, b))) => {
+                    Some(a + (b - a) * (i - j) as f64 / (k - j) as f64)
+                }
+                ("forward", Some((_, a)), _) => Some(a),
+                ("backward", _, Some((_, b))) => Some(b),
+                // Edge gap: nothing to bracket it on the needed side
+                (_, prev, next) if fill_edges => prev.or(next).map(|(_, v)| v),
+                _ => None,
+            };
+            let mut record = item.clone();
+            if let (Some(v), Some(obj)) = (estimate, record.as_object_mut()) {
+                obj.insert(field.to_string(), json!(v));
+                filled += 1;
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "filled": filled,
+    }))
+}
+
+/// Flag records whose change from the previous record exceeds `threshold`.
+///
+/// `mode` is `absolute` (default; `|current - previous|`) or `percent`
+/// (`|change| / |previous| * 100`, never flagged when previous is zero).
+/// Pairs where either value is missing or non-numeric are skipped.
+fn detect_jumps(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for detect_jumps operation".into(),
+        ))?;
+    let threshold =
+        input
+            .get("threshold")
+            .and_then(|v| v.as_f64())
+            .ok_or(TransformError::WrongType(
+                "'threshold' must be a number".into(),
+            ))?;
+    let percent = match input.get("mode").and_then(|v| v.as_str()) {
+        None | Some("absolute") => false,
+        Some("percent") => true,
+        Some(other) => return Err(format!("Unknown mode: {other}. Use: absolute, percent").into()),
+    };
+
+    let mut checked = 0;
+    let mut jumps: Vec<Value> = Vec::new();
+    for (i, pair) in data.windows(2).enumerate() {
+        let (Some(previous), Some(current)) = (
+            numeric_field(&pair[0], field),
+            numeric_field(&pair[1], field),
+        ) else {
+            continue;
+        };
+        checked += 1;
+        let delta = current - previous;
+        let pct_change = safe_div(delta, previous.abs()).map(|r| r * 100.0);
+        let magnitude = if percent {
+            pct_change.map(f64::abs)
+        } else {
+            Some(delta.abs())
+        };
+        if magnitude.is_some_and(|m| m > threshold) {
+            jumps.push(json!({
+                "index": i + 1,
+                "previous": previous,
+                "current": current,
+                "delta": delta,
+                "pct_change": pct_change,
+                "record": pair[1],
+            }));
+        }
+    }
+
+    Ok(json!({
+        "jumps": jumps,
+        "jump_count": jumps.len(),
+        "pairs_checked": checked,
+    }))
+}
+
+/// Largest `n` a weighted sample may ask for.
+const MAX_SAMPLE_SIZE: u64 = 100_000;
+
+/// Draw `n` records (at most `MAX_SAMPLE_SIZE`) with probability
+/// proportional to `weight_field`.
+///
+/// Records with a missing or non-positive weight are never drawn. Without
+/// `replace` each record is drawn at most once, so fewer than `n` come back
+/// when not enough records are eligible. The same `seed` always produces the
+/// same sample.
+fn weighted_sample(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let weight_field =
+        input
+            .get("weight_field")
+            .and_then(|v| v.as_str())
+            .ok_or(TransformError::MissingField(
+                "'weight_field' is required for weighted_sample operation".into(),
+            ))?;
+    let n = input
+        .get("n")
+        .and_then(|v| v.as_u64())
+        .ok_or(TransformError::WrongType(
+            "'n' must be a non-negative integer".into(),
+        ))?;
+    if n > MAX_SAMPLE_SIZE {
+        return Err(format!("'n' must be at most {MAX_SAMPLE_SIZE}").into());
+    }
+    let n = n as usize;
+    let seed = input.get("seed").and_then(|v| v.as_u64()).unwrap_or(0);
+    let replace = input
+        .get("replace")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let pool: Vec<(&Value, f64)> = data
+        .iter()
+        .filter_map(|item| {
+            numeric_field(item, weight_field)
+                .filter(|w| w.is_finite() && *w > 0.0)
+                .map(|w| (item, w))
+        })
+        .collect();
+    let eligible = pool.len();
+
+    let mut rng = SplitMix64(seed);
+    let sample: Vec<Value> = if pool.is_empty() {
+        Vec::new()
+    } else if replace {
+        // Binary search a cumulative-weight table for each draw
+        let cumulative: Vec<f64> = pool
+            .iter()
+            .scan(0.0, |total, (_, w)| {
+                *total += w;
+                Some(*total)
+            })
+            .collect();
+        let total = cumulative[cumulative.len() - 1];
+        (0..n)
+            .map(|_| {
+                let target = rng.next_f64() * total;
+                // Clamp in case rounding leaves `target` past the last entry
+                let chosen = cumulative
+                    .partition_point(|&c| c <= target)
+                    .min(pool.len() - 1);
+                pool[chosen].0.clone()
+            })
+            .collect()
+    } else {
+        // Efraimidis–Spirakis: the n largest keys ln(u) / w are a weighted
+        // sample without replacement, in draw order
+        let mut keyed: Vec<(f64, &Value)> = pool
+            .iter()
+            .map(|(item, w)| (rng.next_f64().ln() / w, *item))
+            .collect();
+        keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+        keyed
+            .into_iter()
+            .take(n.min(eligible))
+            .map(|(_, item)| item.clone())
+            .collect()
+    };
+
+    Ok(json!({
+        "data": sample,
+        "count": sample.len(),
+        "eligible": eligible,
+        "seed": seed,
+    }))
+}
+
+/// Small deterministic PRNG (SplitMix64) so sampling is reproducible by seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Reshape long records into a wide table: one row per distinct `index`
+/// value, with a key for each distinct `columns` value holding the `agg`
+/// ("sum", the default, "mean" or "count") of the `values` field.
+///
+/// Rows and column keys appear in first-seen order; column keys use the
+/// value's text (missing → "null"). Combinations with no data get `fill`
+/// (default null).
+fn pivot(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let required = |key: &str| {
+        input.get(key).and_then(|v| v.as_str()).ok_or_else(|| {
+            TransformError::MissingField(format!("'{key}' field is required for pivot operation"))
+        })
+    };
+    let index = required("index")?;
+    let columns = required("columns")?;
+    let agg = input.get("agg").and_then(|v| v.as_str()).unwrap_or("sum");
+    if !matches!(agg, "sum" | "mean" | "count") {
+        return Err(format!("Unknown agg: {agg}. Use: sum, mean, count").into());
+    }
+    let values = match agg {
+        "count" => None,
+        _ => Some(required("values")?),
+    };
+    let fill = input.get("fill").cloned().unwrap_or(Value::Null);
+
+    let column_of = |item: &Value| count_key(field_value(item, columns).unwrap_or(&Value::Null));
+    let mut column_keys: Vec<String> = Vec::new();
+    for key in data.iter().map(column_of) {
+        if !column_keys.contains(&key) {
+            column_keys.push(key);
+        }
+    }
+
+    let rows: Vec<Value> = group_records(data, index)
+        .into_iter()
+        .map(|(index_value, items)| {
+            let mut row = serde_json::Map::new();
+            row.insert(index.to_string(), index_value);
+            for column in &column_keys {
+                let cell: Vec<&Value> = items
+                    .iter()
+                    .copied()
+                    .filter(|item| column_of(item) == *column)
+                    .collect();
+                let numbers: Vec<f64> = values
+                    .map(|field| field_values(cell.iter().copied(), field))
+                    .unwrap_or_default();
+                let aggregated = match agg {
+                    "count" if !cell.is_empty() => json!(cell.len()),
+                    "sum" if !numbers.is_empty() => json!(numbers.iter().sum::<f64>()),
+                    "mean" if !numbers.is_empty() => {
+                        json!(safe_div(numbers.iter().sum(), numbers.len() as f64))
+                    }
+                    _ => fill.clone(),
+                };
+                row.insert(column.clone(), aggregated);
+            }
+            Value::Object(row)
+        })
+        .collect();
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "columns": column_keys,
+    }))
+}
+
+/// Contingency table of `rows` × `cols` values.
+///
+/// Labels appear in first-seen order (missing fields are labelled null).
+/// `normalize` adds a `proportions` matrix dividing each cell by its row
+/// total, column total, or the grand total.
+fn crosstab(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let rows = input
+        .get("rows")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'rows' field is required for crosstab operation".into(),
+        ))?;
+    let cols = input
+        .get("cols")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'cols' field is required for crosstab operation".into(),
+        ))?;
+    let normalize = input.get("normalize").and_then(|v| v.as_str());
+    if let Some(mode) = normalize.filter(|m| !matches!(*m, "row" | "column" | "total")) {
+        return Err(format!("Unknown normalize mode: {mode}. Use: row, column, total").into());
+    }
+
+    let row_groups = group_records(data, rows);
+    let col_labels: Vec<Value> = group_records(data, cols)
+        .into_iter()
+        .map(|(label, _)| label)
+        .collect();
+    let col_index: std::collections::HashMap<String, usize> = col_labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| (canonical_json(label), i))
+        .collect();
+
+    let mut counts = vec![vec![0usize; col_labels.len()]; row_groups.len()];
+    for (r, (_, records)) in row_groups.iter().enumerate() {
+        for record in records {
+            let label = canonical_json(field_value(record, cols).unwrap_or(&Value::Null));
+            let &c = col_index.get(&label).ok_or_else(|| {
+                TransformError::InvalidValue(format!("No column for label {label}"))
+            })?;
+            counts[r][c] += 1;
+        }
+    }
+    let row_totals: Vec<usize> = counts.iter().map(|row| row.iter().sum()).collect();
+    let col_totals: Vec<usize> = (0..col_labels.len())
+        .map(|c| counts.iter().map(|row| row[c]).sum())
+        .collect();
+
+    let mut result = json!({
+        "row_labels": row_groups.iter().map(|(label, _)| label).collect::<Vec<_>>(),
+        "col_labels": col_labels,
+        "counts": counts,
+        "row_totals": row_totals,
+        "col_totals": col_totals,
+        "total": data.len(),
+    });
+    if let Some(mode) = normalize {
+        let proportions: Vec<Vec<Option<f64>>> = counts
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(c, &count)| {
+                        let denom = match mode {
+                            "row" => row_totals[r],
+                            "column" => col_totals[c],
+                            _ => data.len(),
+                        };
+                        safe_div(count as f64, denom as f64)
+                    })
+                    .collect()
+            })
+            .collect();
+        result["proportions"] = json!(proportions);
+    }
+    Ok(result)
+}
+
+/// Melt wide records into long format: one output record per value field,
+/// carrying the `id_fields` plus `variable_name` (default "variable") and
+/// `value_name` (default "value"). Without `value_fields` every non-id field
+/// is melted; a value field missing from a record yields null.
+fn unpivot(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let id_fields: Vec<&str> = input
+        .get("id_fields")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|f| f.as_str()).collect())
+        .unwrap_or_default();
+    let value_fields: Option<Vec<&str>> = input
+        .get("value_fields")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|f| f.as_str()).collect());
+    let variable_name = input
+        .get("variable_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("variable");
+    let value_name = input
+        .get("value_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+    if id_fields.contains(&variable_name) || id_fields.contains(&value_name) {
+        return Err("'variable_name' and 'value_name' must not repeat an id field".into());
+    }
+
+    let mut rows: Vec<Value> = Vec::new();
+    for item in data {
+        let obj = item
+            .as_object()
+            .ok_or("unpivot requires an array of objects")?;
+        let melted: Vec<&str> = match &value_fields {
+            Some(fields) => fields.clone(),
+            None => obj
+                .keys()
+                .map(|k| k.as_str())
+                .filter(|k| !id_fields.contains(k))
+                .collect(),
+        };
+        for field in melted {
+            let mut row = serde_json::Map::new();
+            for id in &id_fields {
+                row.insert(id.to_string(), obj.get(*id).cloned().unwrap_or(Value::Null));
+            }
+            row.insert(variable_name.to_string(), json!(field));
+            row.insert(
+                value_name.to_string(),
+                obj.get(field).cloned().unwrap_or(Value::Null),
+            );
+            rows.push(Value::Object(row));
+        }
+    }
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Gini coefficient of `field` (0 = perfect equality, approaching 1 = all
+/// value held by one record). Gini is undefined for negative values, so they
+/// are skipped and counted in `negative_skipped`; the coefficient is null
+/// when nothing (or only zeros) remains. With `"lorenz": true` the Lorenz
+/// curve points are included, starting at the origin.
+fn gini(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+    let lorenz = input
+        .get("lorenz")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let all = field_values(data.iter(), field);
+    let mut values: Vec<f64> = all.iter().copied().filter(|v| *v >= 0.0).collect();
+    let negative_skipped = all.len() - values.len();
+    values.sort_by(|a, b| a.total_cmp(b));
+
+    let n = values.len() as f64;
+    let total: f64 = values.iter().sum();
+    let ranked: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i + 1) as f64 * v)
+        .sum();
+    let coefficient = safe_div(2.0 * ranked, n * total).map(|g| g - (n + 1.0) / n);
+
+    let mut result = json!({
+        "gini": coefficient,
+        "count": values.len(),
+        "negative_skipped": negative_skipped,
+    });
+    if negative_skipped > 0 {
+        result["note"] = json!("Negative values were skipped; Gini is undefined for them");
+    }
+    if lorenz {
+        let mut cumulative = 0.0;
+        let mut points = vec![json!({"population_share": 0.0, "value_share": 0.0})];
+        for (i, v) in values.iter().enumerate() {
+            cumulative += v;
+            points.push(json!({
+                "population_share": (i + 1) as f64 / n,
+                "value_share": safe_div(cumulative, total),
+            }));
+        }
+        result["lorenz"] = json!(points);
+    }
+    Ok(result)
+}
+
+/// Field-level diff from object `a` to object `b`: keys `added`, `removed`
+/// and `changed` (with `old` and `new` values). Nested objects are compared
+/// key by key and reported with dotted paths; arrays and other values are
+/// compared whole.
+fn object_diff(input: &Value) -> Result<Value, TransformError> {
+    let a = input
+        .get("a")
+        .and_then(|v| v.as_object())
+        .ok_or(TransformError::WrongType("'a' must be an object".into()))?;
+    let b = input
+        .get("b")
+        .and_then(|v| v.as_object())
+        .ok_or(TransformError::WrongType("'b' must be an object".into()))?;
+
+    let mut diff = ObjectDiff::default();
+    diff_objects(a, b, "", &mut diff);
+    let identical = diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty();
+    Ok(json!({
+        "added": diff.added,
+        "removed": diff.removed,
+        "changed": diff.changed,
+        "identical": identical,
+    }))
+}
+
+#[derive(Default)]
+struct ObjectDiff {
+    added: Vec<Value>,
+    removed: Vec<Value>,
+    changed: Vec<Value>,
+}
+
+fn diff_objects(
+    a: &serde_json::Map<String, Value>,
+    b: &serde_json::Map<String, Value>,
+    prefix: &str,
+    diff: &mut ObjectDiff,
+) {
+    let path = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+    for (key, old) in a {
+        match (old, b.get(key)) {
+            (_, None) => diff.removed.push(json!({"path": path(key), "value": old})),
+            (Value::Object(old), Some(Value::Object(new))) => {
+                diff_objects(old, new, &path(key), diff)
+            }
+            (_, Some(new)) if old != new => diff
+                .changed
+                .push(json!({"path": path(key), "old": old, "new": new})),
+            _ => {}
+        }
+    }
+    for (key, new) in b {
+        if !a.contains_key(key) {
+            diff.added.push(json!({"path": path(key), "value": new}));
+        }
+    }
+}
+
+/// Running total of `field`, written into `output` (default "cumulative")
+/// on each record in input order. Records without a numeric value carry the
+/// previous total forward. With `reset_on`, the total restarts whenever that
+/// field's value differs from the previous record's.
+fn cumulative_sum(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("cumulative");
+    let reset_on = match input.get("reset_on") {
+        None => None,
+        Some(v) => Some(v.as_str().ok_or(TransformError::WrongType(
+            "'reset_on' must be a field name".into(),
+        ))?),
+    };
+
+    let mut total = 0.0;
+    let mut group: Option<String> = None;
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            if let Some(key_field) = reset_on {
+                let key = canonical_json(field_value(item, key_field).unwrap_or(&Value::Null));
+                if group.as_ref() != Some(&key) {
+                    total = 0.0;
+                    group = Some(key);
+                }
+            }
+            total += item
+                .as_f64()
+                .or_else(|| numeric_field(item, field))
+                .unwrap_or(0.0);
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(total));
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Trailing mean of `field` over the last `window` records, written into
+/// `output` (default "moving_average") on each record and also returned as
+/// `values`, one per record.
+///
+/// With `"partial": true` (the default) the first records average over the
+/// shorter window available so far; with false they get null until the
+/// window fills. Records without a numeric value don't count toward the
+/// mean, and a window with no numbers at all gives null.
+fn moving_average(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+    let window = input
+        .get("window")
+        .and_then(|v| v.as_u64())
+        .filter(|&w| w >= 1)
+        .ok_or(TransformError::InvalidValue(
+            "'window' must be an integer of at least 1".into(),
+        ))? as usize;
+    let partial = input
+        .get("partial")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("moving_average");
+
+    let points: Vec<Option<f64>> = data
+        .iter()
+        .map(|item| item.as_f64().or_else(|| numeric_field(item, field)))
+        .collect();
+    let averages: Vec<Option<f64>> = (0..points.len())
+        .map(|i| {
+            if !partial && i + 1 < window {
+                return None;
+            }
+            let start = (i + 1).saturating_sub(window);
+            let numbers: Vec<f64> = points[start..=i].iter().flatten().copied().collect();
+            safe_div(numbers.iter().sum(), numbers.len() as f64)
+        })
+        .collect();
+
+    let rows: Vec<Value> = data
+        .iter()
+        .zip(&averages)
+        .map(|(item, average)| {
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(average));
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "values": averages,
+        "window": window,
+    }))
+}
+
+/// Rescale `field` into `output` (default "normalized") with `method`
+/// "minmax" (to [0, 1], the default) or "zscore" (population std_dev).
+///
+/// A zero range or std_dev gives 0 for every record instead of NaN. Records
+/// without a numeric value are left unchanged and counted in `missing`.
+fn normalize_field(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for normalize operation".into(),
+        ))?;
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("normalized");
+    let method = input
+        .get("method")
+        .and_then(|v| v.as_str())
+        .unwrap_or("minmax");
+
+    let values = field_values(data.iter(), field);
+    let count = values.len() as f64;
+    // Each method reduces to (x - center) / scale
+    let (center, scale) = match method {
+        "minmax" => {
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            (min, max - min)
+        }
+        "zscore" => {
+            // Undefined statistics give a zero scale, which maps every value to 0
+            let mean = safe_div(values.iter().sum(), count).unwrap_or(0.0);
+            let variance = safe_div(values.iter().map(|v| (v - mean).powi(2)).sum(), count);
+            (mean, variance.map_or(0.0, f64::sqrt))
+        }
+        other => {
+            return Err(format!("Unknown method: {other}. Use: minmax, zscore").into());
+        }
+    };
+
+    let mut missing = 0;
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let mut record = item.clone();
+            match (numeric_field(item, field), record.as_object_mut()) {
+                (Some(v), Some(obj)) => {
+                    let scaled = safe_div(v - center, scale).unwrap_or(0.0);
+                    obj.insert(output.to_string(), json!(scaled));
+                }
+                _ => missing += 1,
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "missing": missing,
+    }))
+}
+
+/// Find records whose `field` falls outside fences computed by `method`:
+/// "iqr" (the default, `[Q1 - 1.5·IQR, Q3 + 1.5·IQR]`) or "zscore"
+/// (`mean ± threshold·std_dev`, threshold 3 by default).
+///
+/// Returns the `outliers` and the `bounds` used. `action` "flag" (the
+/// default) sets `outlier` true/false on each record with a numeric value;
+/// "remove" returns only the records that aren't outliers.
+fn outliers(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .unwrap_or("value");
+    let remove = match input.get("action").and_then(|v| v.as_str()) {
+        None | Some("flag") => false,
+        Some("remove") => true,
+        Some(other) => {
+            return Err(format!("Unknown action: {other}. Use: flag, remove").into());
+        }
+    };
+
+    let values = field_values(data.iter(), field);
+    let mut bounds = serde_json::Map::new();
+    let (lower, upper) = match input.get("method").and_then(|v| v.as_str()) {
+        _ if values.is_empty() => (f64::NEG_INFINITY, f64::INFINITY),
+        None | Some("iqr") => {
+            let quartiles = percentile_map(values.clone(), &[25.0, 75.0]);
+            let (q1, q3) = (
+                quartiles["25"].as_f64().unwrap_or_default(),
+                quartiles["75"].as_f64().unwrap_or_default(),
+            );
+            let iqr = q3 - q1;
+            bounds.insert("q1".into(), json!(q1));
+            bounds.insert("q3".into(), json!(q3));
+            bounds.insert("iqr".into(), json!(iqr));
+            (q1 - 1.5 * iqr, q3 + 1.5 * iqr)
+        }
+        Some("zscore") => {
+            let threshold = match input.get("threshold") {
+                None => 3.0,
+                Some(t) => t.as_f64().ok_or("'threshold' must be a number")?,
+            };
+            let count = values.len() as f64;
+            let mean = safe_div(values.iter().sum(), count);
+            let std_dev = mean.and_then(|m| {
+                safe_div(values.iter().map(|v| (v - m).powi(2)).sum(), count).map(f64::sqrt)
+            });
+            bounds.insert("mean".into(), json!(mean));
+            bounds.insert("std_dev".into(), json!(std_dev));
+            bounds.insert("threshold".into(), json!(threshold));
+            match (mean, std_dev) {
+                (Some(m), Some(sd)) => (m - threshold * sd, m + threshold * sd),
+                // Undefined spread: nothing can be flagged
+                _ => (f64::NEG_INFINITY, f64::INFINITY),
+            }
+        }
+        Some(other) => {
+            return Err(format!("Unknown method: {other}. Use: iqr, zscore").into());
+        }
+    };
+    if !values.is_empty() {
+        bounds.insert("lower".into(), json!(lower));
+        bounds.insert("upper".into(), json!(upper));
+    }
+
+    let value_of = |item: &Value| item.as_f64().or_else(|| numeric_field(item, field));
+    let is_outlier = |v: f64| v < lower || v > upper;
+    let flagged: Vec<&Value> = data
+        .iter()
+        .filter(|item| value_of(item).is_some_and(is_outlier))
+        .collect();
+    let rows: Vec<Value> = if remove {
+        data.iter()
+            .filter(|item| !value_of(item).is_some_and(is_outlier))
+            .cloned()
+            .collect()
+    } else {
+        data.iter()
+            .map(|item| {
+                let mut record = item.clone();
+                if let (Some(v), Some(obj)) = (value_of(item), record.as_object_mut()) {
+                    obj.insert("outlier".into(), json!(is_outlier(v)));
+                }
+                record
+            })
+            .collect()
+    };
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "outliers": flagged,
+        "outlier_count": flagged.len(),
+        "bounds": bounds,
+    }))
+}
+
+/// The `n` records with the highest numeric `field` (lowest with
+/// `"bottom": true`), best first. Uses a partial selection, so only the
+/// kept records are fully sorted. Ties go to the earlier record; records
+/// without a numeric value are never selected.
+fn top_n(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for top_n operation".into(),
+        ))?;
+    let n = input
+        .get("n")
+        .and_then(|v| v.as_u64())
+        .ok_or("'n' must be a non-negative integer")? as usize;
+    let bottom = input
+        .get("bottom")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let mut ranked: Vec<(f64, usize)> = data
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| Some((item.as_f64().or_else(|| numeric_field(item, field))?, i)))
+        .collect();
+    let order = |a: &(f64, usize), b: &(f64, usize)| {
+        let by_value = if bottom {
+            a.0.total_cmp(&b.0)
+        } else {
+            b.0.total_cmp(&a.0)
+        };
+        by_value.then(a.1.cmp(&b.1))
+    };
+    if n < ranked.len() {
+        if n > 0 {
+            ranked.select_nth_unstable_by(n - 1, order);
+        }
+        ranked.truncate(n);
+    }
+    ranked.sort_unstable_by(order);
+
+    let rows: Vec<&Value> = ranked.iter().map(|&(_, i)| &data[i]).collect();
+    Ok(json!({"data": rows, "count": rows.len()}))
+}
+
+/// Join the `left` and `right` record arrays on `on` — a field name, or
+/// `{"left": ..., "right": ...}` when the key fields are named differently.
+///
+/// `type` is "inner" (the default), "left" or "outer". Each matching pair is
+/// merged into one object, left fields first; unmatched rows kept by a left
+/// or outer join get null for every field the other side has. Records whose
+/// key is missing or null never match.
+///
+/// When both sides have a field (other than a shared key), the left value
+/// wins and the field name is listed under `conflicts`.
+fn join_records(input: &Value) -> Result<Value, TransformError> {
+    let left = array_field(input, "left")?;
+    let right = array_field(input, "right")?;
+    let (left_key, right_key) = match input.get("on") {
+        Some(Value::String(key)) => (key.as_str(), key.as_str()),
+        Some(on) => match (
+            on.get("left").and_then(|v| v.as_str()),
+            on.get("right").and_then(|v| v.as_str()),
+        ) {
+            (Some(l), Some(r)) => (l, r),
+            _ => {
+                return Err(TransformError::WrongType(
+                    "'on' must be a field name or {\"left\": ..., \"right\": ...}".into(),
+                ))
+            }
+        },
+        None => {
+            return Err(TransformError::MissingField(
+                "'on' is required for join operation".into(),
+            ))
+        }
+    };
+    let (keep_left, keep_right) = match input.get("type").and_then(|v| v.as_str()) {
+        None | Some("inner") => (false, false),
+        Some("left") => (true, false),
+        Some("outer") => (true, true),
+        Some(other) => {
+            return Err(format!("Unknown join type: {other}. Use: inner, left, outer").into());
+        }
+    };
+
+    let key_of = |item: &Value, field: &str| {
+        field_value(item, field)
+            .filter(|v| !v.is_null())
+            .map(canonical_json)
+    };
+    let mut index: std::collections::HashMap<String, Vec<usize>> = std::collections::HashMap::new();
+    for (i, item) in right.iter().enumerate() {
+        if let Some(key) = key_of(item, right_key) {
+            index.entry(key).or_default().push(i);
+        }
+    }
+    // Every field each side has, for null-filling unmatched rows
+    let fields_of = |records: &[Value]| {
+        let mut fields: Vec<String> = Vec::new();
+        for key in records
+            .iter()
+            .filter_map(|r| r.as_object())
+            .flat_map(|o| o.keys())
+        {
+            if !fields.contains(key) {
+                fields.push(key.clone());
+            }
+        }
+        fields
+    };
+    let (left_fields, right_fields) = (fields_of(left), fields_of(right));
+    let empty = serde_json::Map::new();
+
+    let mut rows: Vec<Value> = Vec::new();
+    let mut conflicts: Vec<String> = Vec::new();
+    let mut matched_right = vec![false; right.len()];
+    for item in left {
+        let record = item.as_object().unwrap_or(&empty);
+        let matches = key_of(item, left_key)
+            .and_then(|key| index.get(&key))
+            .map_or(&[][..], |m| m.as_slice());
+        for &j in matches {
+            matched_right[j] = true;
+            let mut merged = record.clone();
+            for (field, value) in right[j].as_object().unwrap_or(&empty) {
+                if merged.contains_key(field) {
+                    let shared_key = field == left_key && field == right_key;
+                    if !shared_key && !conflicts.contains(field) {
+                        conflicts.push(field.clone());
+                    }
+                } else {
+                    merged.insert(field.clone(), value.clone());
+                }
+            }
+            rows.push(Value::Object(merged));
+        }
+        if matches.is_empty() && keep_left {
+            let mut row = record.clone();
+            for field in &right_fields {
+                row.entry(field.clone()).or_insert(Value::Null);
+            }
+            rows.push(Value::Object(row));
+        }
+    }
+    if keep_right {
+        for (item, _) in right.iter().zip(&matched_right).filter(|(_, m)| !**m) {
+            let mut row: serde_json::Map<String, Value> = left_fields
+                .iter()
+                .map(|f| (f.clone(), Value::Null))
+                .collect();
+            for (field, value) in item.as_object().unwrap_or(&empty) {
+                row.insert(field.clone(), value.clone());
+            }
+            rows.push(Value::Object(row));
+        }
+    }
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "conflicts": conflicts,
+    }))
+}
+
+/// Write each record's z-score `(x - mean) / std_dev` of `field` into
+/// `output` (default "zscore"), using the population standard deviation.
+///
+/// When every value is the same the z-scores are 0, or null with
+/// `"zero_variance": "null"`. Records without a numeric value get null.
+fn zscore(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let field = input
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::MissingField(
+            "'field' is required for zscore operation".into(),
+        ))?;
+    let output = input
+        .get("output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("zscore");
+    let null_on_zero_variance = match input.get("zero_variance").and_then(|v| v.as_str()) {
+        None | Some("zero") => false,
+        Some("null") => true,
+        Some(other) => {
+            return Err(format!("Unknown zero_variance mode: {other}. Use: zero, null").into())
+        }
+    };
+
+    let values = field_values(data.iter(), field);
+    let count = values.len() as f64;
+    let mean = safe_div(values.iter().sum(), count);
+    let std_dev = mean.and_then(|m| {
+        safe_div(values.iter().map(|v| (v - m).powi(2)).sum::<f64>(), count).map(f64::sqrt)
+    });
+
+    let rows: Vec<Value> = data
+        .iter()
+        .map(|item| {
+            let score = match (numeric_field(item, field), mean, std_dev) {
+                (Some(v), Some(m), Some(sd)) if sd > 0.0 => Some((v - m) / sd),
+                (Some(_), Some(_), Some(_)) if !null_on_zero_variance => Some(0.0),
+                _ => None,
+            };
+            let mut record = item.clone();
+            if let Some(obj) = record.as_object_mut() {
+                obj.insert(output.to_string(), json!(score));
+            }
+            record
+        })
+        .collect();
+
+    Ok(json!({
+        "data": rows,
+        "count": rows.len(),
+        "mean": mean,
+        "std_dev": std_dev,
+    }))
+}
+
+/// Parse CSV text in `data` into records keyed by the header row.
+///
+/// Cells listed in `null_values` (default: just the empty cell) become null;
+/// quoted cells are always kept as strings, so `"NA"` survives as text.
+/// Unquoted numbers and `true`/`false` are typed unless `infer_types` is
+/// false. Without a `header` row, columns are named `column_1`, `column_2`, …
+fn csv_parse(input: &Value) -> Result<Value, TransformError> {
+    let text = input
+        .get("data")
+        .and_then(|v| v.as_str())
+        .ok_or(TransformError::WrongType(
+            "'data' must be a CSV string for csv_parse".into(),
+        ))?;
+    let delimiter = csv_delimiter(input)?;
+    let header = input
+        .get("header")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let infer_types = input
+        .get("infer_types")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let null_values: Vec<&str> = match input.get("null_values") {
+        Some(v) => v
+            .as_array()
+            .ok_or(TransformError::WrongType(
+                "'null_values' must be an array of strings".into(),
+            ))?
+            .iter()
+            .filter_map(|s| s.as_str())
+            .collect(),
+        None => vec![""],
+    };
+
+    let mut rows = parse_csv_rows(text, delimiter)?.into_iter();
+    let columns: Vec<String> = if header {
+        rows.next()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|cell| cell.text)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut records: Vec<Value> = Vec::new();
+    let mut column_count = columns.len();
+    for (i, row) in rows.enumerate() {
+        if header && row.len() > columns.len() {
+            return Err(format!(
+                "Row {} has {} cells but the header has {}",
+                i + 1,
+                row.len(),
+                columns.len()
+            )
+            .into());
+        }
+        column_count = column_count.max(row.len());
+        let mut record = serde_json::Map::new();
+        for (c, cell) in row.into_iter().enumerate() {
+            let value = if !cell.quoted && null_values.contains(&cell.text.as_str()) {
+                Value::Null
+            } else if !cell.quoted && infer_types {
+                infer_csv_value(cell.text)
+            } else {
+                Value::String(cell.text)
+            };
+            record.insert(csv_column_name(&columns, c), value);
+        }
+        // Short rows are padded with nulls
+        for column in columns.iter().skip(record.len()) {
+            record.insert(column.clone(), Value::Null);
+        }
+        records.push(Value::Object(record));
+    }
+
+    let columns: Vec<String> = (0..column_count)
+        .map(|c| csv_column_name(&columns, c))
+        .collect();
+    Ok(json!({"data": records, "count": records.len(), "columns": columns}))
+}
+
+/// Format records as CSV text with a header row.
+///
+/// Columns are `fields`, or every key in first-seen order. Null and missing
+/// values are written as `null_output` (default empty); strings that would
+/// read back as null are quoted so the text round-trips through csv_parse.
+/// Nested arrays and objects are written as JSON.
+fn csv_format(input: &Value) -> Result<Value, TransformError> {
+    let data = array_field(input, "data")?;
+    let delimiter = csv_delimiter(input)?;
+    let null_output = input
+        .get("null_output")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let columns: Vec<String> = match input.get("fields").and_then(|v| v.as_array()) {
+        Some(fields) => fields
+            .iter()
+            .filter_map(|f| f.as_str())
+            .map(String::from)
+            .collect(),
+        None => {
+            let mut keys: Vec<String> = Vec::new();
+            for key in data
+                .iter()
+                .filter_map(|r| r.as_object())
+                .flat_map(|o| o.keys())
+            {
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+            keys
+        }
+    };
+
+    let separator = delimiter.to_string();
+    let mut lines = vec![columns
+        .iter()
+        .map(|c| csv_quote(c, delimiter, false))
+        .collect::<Vec<_>>()
+        .join(&separator)];
+    for item in data {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(
+                |column| match field_value(item, column).unwrap_or(&Value::Null) {
+                    Value::Null => null_output.to_string(),
+                    Value::String(text) => csv_quote(text, delimiter, text == null_output),
+                    other => csv_quote(&other.to_string(), delimiter, false),
+                },
+            )
+            .collect();
+        lines.push(cells.join(&separator));
+    }
+
+    Ok(json!({"data": lines.join("\n") + "\n", "count": data.len(), "columns": columns}))
+}
+
+/// One parsed CSV cell; `quoted` cells skip null and type detection.
+struct CsvCell {
+    text: String,
+    quoted: bool,
+}
+
+/// Split CSV text into rows of cells (RFC 4180 quoting, `\n` or `\r\n`).
+fn parse_csv_rows(text: &str, delimiter: char) -> Result<Vec<Vec<CsvCell>>, TransformError> {
+    let mut rows: Vec<Vec<CsvCell>> = Vec::new();
+    let mut row: Vec<CsvCell> = Vec::new();
+    let mut cell = CsvCell {
+        text: String::new(),
+        quoted: false,
+    };
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.text.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => cell.text.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if cell.text.is_empty() && !cell.quoted => {
+                in_quotes = true;
+                cell.quoted = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::replace(
+                    &mut cell,
+                    CsvCell {
+                        text: String::new(),
+                        quoted: false,
+                    },
+                ));
+                rows.push(std::mem::take(&mut row));
+            }
+            c if c == delimiter => {
+                row.push(std::mem::replace(
+                    &mut cell,
+                    CsvCell {
+                        text: String::new(),
+                        quoted: false,
+                    },
+                ));
+            }
+            _ => cell.text.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quoted field in CSV".into());
+    }
+    if !row.is_empty() || !cell.text.is_empty() || cell.quoted {
+        row.push(cell);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// `delimiter` parameter as a single character (default `,`).
+fn csv_delimiter(input: &Value) -> Result<char, TransformError> {
+    match input.get("delimiter").and_then(|v| v.as_str()) {
+        None => Ok(','),
+        Some(d) => {
+            let mut chars = d.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c != '"' && c != '\n' => Ok(c),
+                _ => Err(format!("Invalid delimiter: {d:?}. Use a single character").into()),
+            }
+        }
+    }
+}
+
+/// Header name for column `index`, or `column_<n>` past the header.
+fn csv_column_name(columns: &[String], index: usize) -> String {
+    columns
+        .get(index)
+        .cloned()
+        .unwrap_or_else(|| format!("column_{}", index + 1))
+}
+
+/// Type an unquoted cell: JSON numbers and `true`/`false`, else a string.
+fn infer_csv_value(text: String) -> Value {
+    match serde_json::from_str::<Value>(&text) {
+        Ok(v @ (Value::Number(_) | Value::Bool(_))) => v,
+        _ => Value::String(text),
+    }
+}
+
+/// Quote a cell when it contains special characters (or `force` is set).
+fn csv_quote(text: &str, delimiter: char, force: bool) -> String {
+    if force || text.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn euclidean_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Cosine distance, or `None` when either vector has zero magnitude.
+fn cosine_distance(a: &[f64], b: &[f64]) -> Option<f64> {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+    safe_div(dot, norm_a * norm_b).map(|similarity| 1.0 - similarity)
+}
+
+/// Divide, returning `None` (serialized as JSON null) when the denominator is
+/// zero or the result isn't finite.
+///
+/// Every aggregate, stats and bucketing path divides through this so NaN
+/// and infinity never leak into output.
+fn safe_div(num: f64, denom: f64) -> Option<f64> {
+    if denom == 0.0 {
+        return None;
+    }
+    let result = num / denom;
+    result.is_finite().then_some(result)
+}
+
+fn numeric_field(item: &Value, field: &str) -> Option<f64> {
+    field_value(item, field).and_then(|v| v.as_f64())
+}
+
+/// Look up `field` in a record. A literal key wins; otherwise a dotted path
+/// like `user.profile.age` walks nested objects, and a missing intermediate
+/// key is treated like a missing field.
+fn field_value<'a>(item: &'a Value, field: &str) -> Option<&'a Value> {
+    item.get(field).or_else(|| {
+        if !field.contains('.') {
+            return None;
+        }
+        field
+            .split('.')
+            .try_fold(item, |value, key| value.as_object()?.get(key))
+    })
+}
+
+fn type_name(v: &Value) -> &str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn stats_basic() {
+        let result = execute(&json!({
+            "operation": "stats",
+            "data": [{"value": 10}, {"value": 20}, {"value": 30}],
+            "field": "value"
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 3);
+        assert_eq!(result["sum"], 60.0);
+        assert_eq!(result["mean"], 20.0);
+        assert_eq!(result["min"], 10.0);
+        assert_eq!(result["max"], 30.0);
+    }
+
+    #[test]
+    fn stats_over_string_lengths() {
+        let result = execute(&json!({
+            "operation": "stats",
+            "data": [{"d": "abc"}, {"d": "héllo"}, {"d": 42}, {"d": ""}],
+            "field": "d",
+            "stat": "length"
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 3);
+        assert_eq!(result["min"], 0.0);
+        assert_eq!(result["max"], 5.0);
+        assert_eq!(result["sum"], 8.0);
+    }
+
+    #[test]
+    fn stats_over_head_and_tail_windows() {
+        let data = json!([1, 2, 3, 4, 10, 20]);
+        let result = execute(&json!({"operation": "stats", "data": data, "tail": 2})).unwrap();
+        assert_eq!(result["count"], 2);
+        assert_eq!(result["mean"], 15.0);
+
+        let result = execute(&json!({"operation": "stats", "data": data, "head": 3})).unwrap();
+        assert_eq!(result["sum"], 6.0);
+
+        let result = execute(&json!({"operation": "stats", "data": data, "tail": 50})).unwrap();
+        assert_eq!(result["count"], 6);
+    }
+
+    #[test]
+    fn stats_summarize_adds_sentence() {
+        let result = execute(&json!({
+            "operation": "stats",
+            "data": [10, 20, 99],
+            "summarize": true
+        }))
+        .unwrap();
+        assert_eq!(
+            result["summary"],
+            "Across 3 records, the mean value is 43 (range 10–99, std dev 39.81)."
+        );
+        assert_eq!(result["mean"], 43.0);
+
+        let empty = execute(&json!({
+            "operation": "stats",
+            "data": [],
+            "field": "price",
+            "summarize": true
+        }))
+        .unwrap();
+        assert_eq!(empty["summary"], "No numeric price values to summarize.");
+    }
+
+    #[test]
+    fn stats_percentiles_exact_and_approximate() {
+        let data: Vec<u32> = (1..=10_000).collect();
+        let exact = execute(&json!({
+            "operation": "stats",
+            "data": [1, 2, 3, 4],
+            "percentiles": [50, 90]
+        }))
+        .unwrap();
+        assert_eq!(exact["percentiles"], json!({"50": 2.5, "90": 3.7}));
+        assert!(exact.get("percentile_error").is_none());
+
+        let approx = execute(&json!({
+            "operation": "stats",
+            "data": data,
+            "percentiles": [50, 90],
+            "approximate": true,
+            "sketch_size": 500
+        }))
+        .unwrap();
+        let error = &approx["percentile_error"];
+        assert_eq!(error["sample_size"], 500);
+        let rank_error = error["rank_error"].as_f64().unwrap();
+        assert!(rank_error > 0.0 && rank_error < 0.1);
+        let median = approx["percentiles"]["50"].as_f64().unwrap();
+        assert!((median - 5000.5).abs() < rank_error * 10_000.0);
+        // Moments are still exact over every value, streamed in one pass
+        assert_eq!(approx["count"], 10_000);
+        assert_eq!(approx["sum"], 50_005_000.0);
+        assert_eq!(approx["min"], 1.0);
+        let std_dev = approx["std_dev"].as_f64().unwrap();
+        assert!((std_dev - 2886.7513).abs() < 1e-3, "{std_dev}");
+        // The median is estimated from the same bounded sample
+        let estimate = approx["median"].as_f64().unwrap();
+        assert!((estimate - 5000.5).abs() < rank_error * 10_000.0);
+    }
+
+    #[test]
+    fn stats_and_aggregate_drop_sentinel_values() {
+        let data = json!([{"v": 10}, {"v": -1}, {"v": 20}, {"v": 9999}, {"v": -1}]);
+        let stats = execute(&json!({
+            "operation": "stats",
+            "data": data,
+            "field": "v",
+            "exclude_values": [-1, 9999]
+        }))
+        .unwrap();
+        assert_eq!(stats["count"], 2);
+        assert_eq!(stats["mean"], 15.0);
+        assert_eq!(stats["excluded_count"], 3);
+
+        let aggregate = execute(&json!({
+            "operation": "aggregate",
+            "data": data,
+            "field": "v",
+            "compute": ["max"],
+            "exclude_values": [9999]
+        }))
+        .unwrap();
+        assert_eq!(
+            aggregate,
+            json!({"count": 4, "max": 20.0, "excluded_count": 1})
+        );
+
+        let err = execute(&json!({"operation": "stats", "data": [], "exclude_values": ["x"]}));
+        assert_eq!(
+            err.unwrap_err(),
+            TransformError::WrongType("'exclude_values' must be an array of numbers".into())
+        );
+    }
+
+    #[test]
+    fn stats_median_and_mode() {
+        let odd = execute(&json!({"operation": "stats", "data": [5, 1, 3, 3]})).unwrap();
+        assert_eq!(odd["median"], 3.0);
+        assert_eq!(odd["mode"], 3.0);
+
+        let even = execute(&json!({"operation": "stats", "data": [4, 1, 2, 4, 1, 9]})).unwrap();
+        assert_eq!(even["median"], 3.0);
+        assert_eq!(even["mode"], json!([1.0, 4.0]));
+
+        let empty = execute(&json!({"operation": "stats", "data": []})).unwrap();
+        assert!(empty["median"].is_null());
+        assert!(empty["mode"].is_null());
+        assert!(empty.get("median").is_some() && empty.get("mode").is_some());
+    }
+
+    #[test]
+    fn stats_percentiles_match_numpy_linear() {
+        let result = execute(&json!({
+            "operation": "stats",
+            "data": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            "percentiles": [0, 90, 95, 99, 100, 12.5]
+        }))
+        .unwrap();
+        let p = &result["percentiles"];
+        assert_eq!(p["0"], 1.0);
+        assert!((p["90"].as_f64().unwrap() - 9.1).abs() < 1e-9);
+        assert!((p["95"].as_f64().unwrap() - 9.55).abs() < 1e-9);
+        assert!((p["99"].as_f64().unwrap() - 9.91).abs() < 1e-9);
+        assert_eq!(p["100"], 10.0);
+        assert!((p["12.5"].as_f64().unwrap() - 2.125).abs() < 1e-9);
+
+        let err = execute(&json!({"operation": "stats", "data": [1], "percentiles": [50, 101]}));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Invalid percentile: 101. Use a number between 0 and 100"
+        );
+        let empty = execute(&json!({"operation": "stats", "data": [], "percentiles": [50]}));
+        assert_eq!(empty.unwrap()["percentiles"], json!({"50": null}));
+    }
+
+    #[test]
+    fn stats_sample_std_dev() {
+        let data = json!([2, 4, 4, 4, 5, 5, 7, 9]);
+        let population = execute(&json!({"operation": "stats", "data": data})).unwrap();
+        assert_eq!(population["std_dev"], 2.0);
+
+        let sample = execute(&json!({"operation": "stats", "data": data, "sample": true})).unwrap();
+        let expected = (32.0f64 / 7.0).sqrt();
+        assert!((sample["std_dev"].as_f64().unwrap() - expected).abs() < 1e-12);
+
+        let single = execute(&json!({"operation": "stats", "data": [3], "sample": true})).unwrap();
+        assert!(single["std_dev"].is_null());
+    }
+
+    #[test]
+    fn filter_missing_mode_controls_absent_fields() {
+        let data = json!([{"v": 1}, {"v": 9}, {"other": 1}, {"v": "n/a"}]);
+        let run = |missing: Value| {
+            let mut input = json!({
+                "operation": "filter",
+                "data": data,
+                "where": {"field": "v", "op": "<", "value": 5}
+            });
+            if !missing.is_null() {
+                input["missing"] = missing;
+            }
+            execute(&input).unwrap()["data"].clone()
+        };
+        assert_eq!(run(Value::Null), json!([{"v": 1}]));
+        assert_eq!(run(json!("exclude")), json!([{"v": 1}]));
+        assert_eq!(
+            run(json!("include")),
+            json!([{"v": 1}, {"other": 1}, {"v": "n/a"}])
+        );
+        assert_eq!(
+            run(json!("zero")),
+            json!([{"v": 1}, {"other": 1}, {"v": "n/a"}])
+        );
+        let zero = execute(&json!({
+            "operation": "filter",
+            "data": data,
+            "where": {"field": "v", "op": ">", "value": 5},
+            "missing": "zero"
+        }))
+        .unwrap();
+        assert_eq!(zero["data"], json!([{"v": 9}]));
+
+        let err = execute(&json!({
+            "operation": "filter",
+            "data": data,
+            "where": {"field": "v", "op": ">", "value": 0},
+            "missing": "skip"
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown missing mode: skip. Use: include, exclude, zero"
+        );
+    }
+
+    #[test]
+    fn filter_equality_is_exact_for_large_integers() {
+        let data = json!([
+            {"id": 9_007_199_254_740_993_u64},
+            {"id": 9_007_199_254_740_992_u64},
+            {"id": 1e9},
+            {"id": 0.30000000000000004},
+            {"id": 0.31}
+        ]);
+        let ids = |value: Value, tolerance: Option<f64>| {
+            let mut clause = json!({"field": "id", "op": "==", "value": value});
+            if let Some(t) = tolerance {
+                clause["tolerance"] = json!(t);
+            }
+            let result =
+                execute(&json!({"operation": "filter", "data": data, "where": clause})).unwrap();
+            result["data"].clone()
+        };
+        assert_eq!(
+            ids(json!(9_007_199_254_740_993_u64), None),
+            json!([{"id": 9_007_199_254_740_993_u64}])
+        );
+        assert_eq!(ids(json!(1_000_000_000), None), json!([{"id": 1e9}]));
+        assert_eq!(ids(json!(0.3), None), json!([{"id": 0.30000000000000004}]));
+        assert_eq!(
+            ids(json!(0.3), Some(0.02)),
+            json!([{"id": 0.30000000000000004}, {"id": 0.31}])
+        );
+    }
+
+    #[test]
+    fn stats_ratio_means() {
+        let result = execute(&json!({
+            "operation": "stats",
+            "data": [1, 2, 4],
+            "ratio_means": true
+        }))
+        .unwrap();
+        assert!((result["geometric_mean"].as_f64().unwrap() - 2.0).abs() < 1e-9);
+        assert!((result["harmonic_mean"].as_f64().unwrap() - 12.0 / 7.0).abs() < 1e-9);
+        assert!(result.get("note").is_none());
+
+        let result = execute(&json!({
+            "operation": "stats",
+            "data": [0, 2, -4],
+            "ratio_means": true
+        }))
+        .unwrap();
+        assert_eq!(result["geometric_mean"], Value::Null);
+        assert_eq!(result["harmonic_mean"], Value::Null);
+        assert_eq!(
+            result["note"],
+            "Geometric mean is undefined for non-positive values. \
+             Harmonic mean is undefined when a value is zero."
+        );
+    }
+
+    #[test]
+    fn filter_greater_than() {
+        let result = execute(&json!({
+            "operation": "filter",
+            "data": [{"x": 5}, {"x": 15}, {"x": 25}],
+            "where": {"field": "x", "op": ">", "value": 10}
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 2);
+    }
+
+    #[test]
+    fn filter_explain_reports_exclusion_reasons() {
+        let result = execute(&json!({
+            "operation": "filter",
+            "data": [{"x": 5}, {"x": 15}, {"y": 1}, {"x": "high"}, {"x": 25}],
+            "where": {"field": "x", "op": ">", "value": 10},
+            "explain": true
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 2);
+        assert_eq!(result["explain"]["excluded"], 3);
+        assert_eq!(result["explain"]["missing_field"], 1);
+        assert_eq!(result["explain"]["type_mismatch"], 1);
+        assert_eq!(result["explain"]["predicate_failed"], 1);
+    }
+
+    #[test]
+    fn filter_compares_strings_and_excludes_mismatches() {
+        let data = json!([
+            {"status": "active"},
+            {"status": "archived"},
+            {"status": "pending"},
+            {"status": 1},
+            {}
+        ]);
+        let filter = |op: &str, value: Value| {
+            execute(&json!({
+                "operation": "filter",
+                "data": data,
+                "where": {"field": "status", "op": op, "value": value},
+                "explain": true
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            filter("==", json!("active"))["data"],
+            json!([{"status": "active"}])
+        );
+        let not_active = filter("!=", json!("active"));
+        assert_eq!(not_active["count"], 2);
+        assert_eq!(not_active["explain"]["missing_field"], 1);
+        assert_eq!(not_active["explain"]["type_mismatch"], 1);
+        assert_eq!(filter("<", json!("b"))["count"], 2);
+        assert_eq!(
+            filter(">=", json!("p"))["data"],
+            json!([{"status": "pending"}])
+        );
+
+        // Numeric clauses no longer treat missing fields as 0
+        let below = execute(&json!({
+            "operation": "filter",
+            "data": [{"x": -1}, {}, {"x": "n/a"}],
+            "where": {"field": "x", "op": "<", "value": 1}
+        }))
+        .unwrap();
+        assert_eq!(below["data"], json!([{"x": -1}]));
+    }
+
+    #[test]
+    fn filter_substring_operators() {
+        let data = json!([
+            {"name": "Alpha Widget"},
+            {"name": "beta gadget"},
+            {"name": "Widget Pro"},
+            {"name": 7}
+        ]);
+        let names = |op: &str, value: &str, ignore_case: bool| -> Vec<Value> {
+            let result = execute(&json!({
+                "operation": "filter",
+                "data": data,
+                "where": {"field": "name", "op": op, "value": value, "ignore_case": ignore_case}
+            }))
+            .unwrap();
+            result["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["name"].clone())
+                .collect()
+        };
+        assert_eq!(
+            names("contains", "Widget", false),
+            [json!("Alpha Widget"), json!("Widget Pro")]
+        );
+        assert_eq!(names("startswith", "widget", false), Vec::<Value>::new());
+        assert_eq!(names("startswith", "widget", true), [json!("Widget Pro")]);
+        assert_eq!(names("endswith", "GADGET", true), [json!("beta gadget")]);
+
+        let err = execute(&json!({
+            "operation": "filter",
+            "data": data,
+            "where": {"field": "name", "op": "contains", "value": 7}
+        }));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "'where.value' must be a string for contains"
+        );
+    }
+
+    #[test]
+    fn dot_paths_reach_nested_fields() {
+        let data = json!([
+            {"user": {"profile": {"age": 30}}, "team": {"name": "a"}},
+            {"user": {"profile": {"age": 50}}, "team": {"name": "b"}},
+            {"user": {}, "team": {"name": "a"}},
+            {"user.profile.age": 10, "team": {"name": "b"}}
+        ]);
+        let stats =
+            execute(&json!({"operation": "stats", "data": data, "field": "user.profile.age"}))
+                .unwrap();
+        assert_eq!(stats["count"], 3);
+        assert_eq!(stats["sum"], 90.0);
+
+        let filtered = execute(&json!({
+            "operation": "filter",
+            "data": data,
+            "where": {"field": "user.profile.age", "op": "<", "value": 40}
+        }))
+        .unwrap();
+        assert_eq!(filtered["count"], 2);
+
+        let sorted = execute(&json!({
+            "operation": "sort",
+            "data": data,
+            "field": "user.profile.age",
+            "descending": true
+        }))
+        .unwrap();
+        assert_eq!(sorted["data"][0]["user"]["profile"]["age"], 50);
+
+        let grouped = execute(&json!({
+            "operation": "aggregate",
+            "data": data,
+            "field": "user.profile.age",
+            "group_by": "team.name",
+            "compute": ["sum"]
+        }))
+        .unwrap();
+        assert_eq!(
+            grouped["groups"][0],
+            json!({"key": "a", "count": 1, "sum": 30.0})
+        );
+        assert_eq!(
+            grouped["groups"][1],
+            json!({"key": "b", "count": 2, "sum": 60.0})
+        );
+    }
+
+    #[test]
+    fn sort_ascending() {
+        let result = execute(&json!({
+            "operation": "sort",
+            "data": [{"v": 30}, {"v": 10}, {"v": 20}],
+            "field": "v"
+        }))
+        .unwrap();
+        let sorted = result["data"].as_array().unwrap();
+        assert_eq!(sorted[0]["v"], 10);
+        assert_eq!(sorted[2]["v"], 30);
+    }
+
+    #[test]
+    fn validate_required_field_missing() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": {"name": "test"},
+            "schema": {"type": "object", "required": ["name", "version"]}
+        }))
+        .unwrap();
+        assert_eq!(result["valid"], false);
+        assert_eq!(result["error_count"], 1);
+    }
+
+    #[test]
+    fn validate_passes() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": {"name": "test", "version": 3},
+            "schema": {"type": "object", "required": ["name", "version"]}
+        }))
+        .unwrap();
+        assert_eq!(result["valid"], true);
+    }
+
+    #[test]
+    fn validate_recurses_into_properties_with_paths() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": {"user": {"name": 5, "address": {}}, "id": "a1"},
+            "schema": {
+                "type": "object",
+                "required": ["user", "id"],
+                "properties": {
+                    "id": {"type": "string"},
+                    "user": {
+                        "type": "object",
+                        "required": ["name", "age"],
+                        "properties": {
+                            "name": {"type": "string"},
+                            "address": {"type": "object", "required": ["city"]}
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(result["valid"], false);
+        assert_eq!(
+            result["errors"],
+            json!([
+                "/user: Missing required field: age",
+                "/user/name: Expected string, got number",
+                "/user/address: Missing required field: city"
+            ])
+        );
+    }
+
+    #[test]
+    fn validate_array_items_and_length() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": [{"name": "a"}, {"name": 2}, {}],
+            "schema": {
+                "type": "array",
+                "maxItems": 2,
+                "items": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {"name": {"type": "string"}}
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            result["errors"],
+            json!([
+                "Expected at most 2 items, got 3",
+                "/1/name: Expected string, got number",
+                "/2: Missing required field: name"
+            ])
+        );
+
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": [],
+            "schema": {"type": "array", "minItems": 1}
+        }))
+        .unwrap();
+        assert_eq!(
+            result["errors"],
+            json!(["Expected at least 1 items, got 0"])
+        );
+    }
+
+    #[test]
+    fn validate_string_constraints() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": ["ab", "abcdef", "x@y.z", "nope"],
+            "schema": {
+                "type": "array",
+                "items": {"type": "string", "minLength": 3, "maxLength": 5, "pattern": "^\\S+@\\S+$"}
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            result["errors"],
+            json!([
+                "/0: String is shorter than minLength 3 (length 2)",
+                "/0: String does not match pattern '^\\S+@\\S+$'",
+                "/1: String is longer than maxLength 5 (length 6)",
+                "/1: String does not match pattern '^\\S+@\\S+$'",
+                "/3: String does not match pattern '^\\S+@\\S+$'"
+            ])
+        );
+
+        let err = execute(&json!({
+            "operation": "validate",
+            "data": {"id": "a"},
+            "schema": {"type": "object", "properties": {"id": {"type": "string", "pattern": "(["}}}
+        }))
+        .unwrap_err()
+        .to_string();
+        assert!(err.starts_with("Invalid pattern '([' at /id:"), "{err}");
+    }
+
+    #[test]
+    fn validate_numeric_bounds_and_integers() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": {"port": 70000, "ratio": 1, "retries": 3.5},
+            "schema": {
+                "type": "object",
+                "properties": {
+                    "port": {"type": "integer", "minimum": 1, "maximum": 65535},
+                    "ratio": {"type": "number", "exclusiveMinimum": 0, "exclusiveMaximum": 1},
+                    "retries": {"type": "integer", "minimum": 0}
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            result["errors"],
+            json!([
+                "/port: 70000 is above maximum 65535",
+                "/ratio: 1 is not below exclusiveMaximum 1",
+                "/retries: Expected integer, got 3.5"
+            ])
+        );
+    }
+
+    #[test]
+    fn validate_enum_uses_json_equality() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": [{"status": "open", "level": 2}, {"status": "Closed", "level": "2"}],
+            "schema": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "status": {"enum": ["open", "closed"]},
+                        "level": {"enum": [1, 2, true]}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            result["errors"],
+            json!([
+                "/1/status: \"Closed\" is not one of: \"open\", \"closed\"",
+                "/1/level: \"2\" is not one of: 1, 2, true"
+            ])
+        );
+    }
+
+    #[test]
+    fn validate_ranges_reports_record_and_field() {
+        let result = execute(&json!({
+            "operation": "validate",
+            "data": [
+                {"age": 30, "score": 88},
+                {"age": 150, "score": 70},
+                {"age": 20, "score": -5},
+                {"age": "old"}
+            ],
+            "ranges": {"age": {"min": 0, "max": 120}, "score": {"min": 0, "max": 100}}
+        }))
+        .unwrap();
+        assert_eq!(result["valid"], false);
+        assert_eq!(result["error_count"], 3);
+        let violations = result["range_violations"].as_array().unwrap();
+        assert_eq!(violations[0]["index"], 1);
+        assert_eq!(violations[0]["field"], "age");
+        assert_eq!(violations[1]["index"], 2);
+        assert_eq!(violations[1]["field"], "score");
+        assert_eq!(violations[2]["index"], 3);
+    }
+
+    #[test]
+    fn aggregate_sum_and_mean() {
+        let result = execute(&json!({
+            "operation": "aggregate",
+            "data": [{"v": 10}, {"v": 20}, {"v": 30}],
+            "field": "v",
+            "compute": ["sum", "mean", "min", "max"]
+        }))
+        .unwrap();
+        assert_eq!(result["sum"], 60.0);
+        assert_eq!(result["mean"], 20.0);
+    }
+
+    #[test]
+    fn merge_objects_recurses_and_overrides_scalars() {
+        let result = execute(&json!({
+            "operation": "merge_objects",
+            "base": {"name": "app", "server": {"port": 80, "host": "localhost"}, "tags": ["a"]},
+            "override": {"server": {"port": 8080}, "tags": ["b"], "debug": true}
+        }))
+        .unwrap();
+        assert_eq!(
+            result["data"],
+            json!({
+                "name": "app",
+                "server": {"port": 8080, "host": "localhost"},
+                "tags": ["b"],
+                "debug": true
+            })
+        );
+    }
+
+    #[test]
+    fn merge_objects_concatenates_arrays_when_requested() {
+        let result = execute(&json!({
+            "operation": "merge_objects",
+            "base": {"tags": ["a"], "nested": {"list": [1]}},
+            "override": {"tags": ["b"], "nested": {"list": [2]}},
+            "array_strategy": "concat"
+        }))
+        .unwrap();
+        assert_eq!(result["data"]["tags"], json!(["a", "b"]));
+        assert_eq!(result["data"]["nested"]["list"], json!([1, 2]));
+    }
+
+    #[test]
+    fn growth_rate_computes_period_over_period_change() {
+        let result = execute(&json!({
+            "operation": "growth_rate",
+            "data": [{"rev": 100}, {"rev": 110}, {"rev": 0}, {"rev": 50}],
+            "field": "rev",
+            "cagr": true
+        }))
+        .unwrap();
+        let rows = result["data"].as_array().unwrap();
+        assert!(rows[0]["growth_rate"].is_null());
+        assert!((rows[1]["growth_rate"].as_f64().unwrap() - 10.0).abs() < 1e-9);
+        assert_eq!(rows[2]["growth_rate"], -100.0);
+        // Previous value of zero means growth is undefined
+        assert!(rows[3]["growth_rate"].is_null());
+        let cagr = result["cagr"].as_f64().unwrap();
+        assert!((cagr - ((0.5f64).powf(1.0 / 3.0) - 1.0) * 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn format_numbers_styles() {
+        let data = json!([{"amount": 1234567.891, "rate": 0.256}, {"amount": -950}]);
+        let result = execute(&json!({
+            "operation": "format_numbers",
+            "data": data,
+            "fields": ["amount"],
+            "style": "thousands"
+        }))
+        .unwrap();
+        assert_eq!(result["data"][0]["amount_formatted"], "1,234,567.89");
+        assert_eq!(result["data"][0]["amount"], 1234567.891);
+        assert_eq!(result["data"][1]["amount_formatted"], "-950.00");
+
+        let result = execute(&json!({
+            "operation": "format_numbers",
+            "data": data,
+            "fields": ["amount"],
+            "style": "compact",
+            "currency_symbol": "$"
+        }))
+        .unwrap();
+        assert_eq!(result["data"][0]["amount_formatted"], "$1.23M");
+
+        let result = execute(&json!({
+            "operation": "format_numbers",
+            "data": data,
+            "fields": ["rate"],
+            "style": "percent",
+            "decimals": 1
+        }))
+        .unwrap();
+        assert_eq!(result["data"][0]["rate_formatted"], "25.6%");
+        assert!(result["data"][1].get("rate_formatted").is_none());
+    }
+
+    #[test]
+    fn compact_number_rounds_before_picking_suffix() {
+        assert_eq!(compact_number(999_999.0, 2), "1.00M");
+        assert_eq!(compact_number(999.999, 2), "1.00K");
+        assert_eq!(compact_number(999_994.0, 2), "999.99K");
+        assert_eq!(compact_number(950.0, 0), "950");
+    }
+
+    #[test]
+    fn format_numbers_rejects_too_many_decimals() {
+        let err = execute(&json!({
+            "operation": "format_numbers",
+            "data": [{"x": 1.5}],
+            "fields": ["x"],
+            "decimals": 100_000_000
+        }))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "'decimals' must be at most 20");
+    }
+
+    #[test]
+    fn find_duplicates_groups_records_by_key() {
+        let result = execute(&json!({
+            "operation": "find_duplicates",
+            "data": [
+                {"email": "a@x.com", "n": 1},
+                {"email": "b@x.com", "n": 2},
+                {"email": "a@x.com", "n": 3},
+                {"email": "a@x.com", "n": 4},
+                {"email": "c@x.com", "n": 5}
+            ],
+            "by": ["email"]
+        }))
+        .unwrap();
+        assert_eq!(result["group_count"], 1);
+        assert_eq!(result["duplicate_count"], 2);
+        assert_eq!(result["groups"][0]["key"], json!({"email": "a@x.com"}));
+        assert_eq!(result["groups"][0]["indices"], json!([0, 2, 3]));
+    }
+
+    #[test]
+    fn find_duplicates_compares_whole_records_by_default() {
+        let result = execute(&json!({
+            "operation": "find_duplicates",
+            "data": [{"a": 1, "b": 2}, {"b": 2, "a": 1}, {"a": 1}]
+        }))
+        .unwrap();
+        assert_eq!(result["group_count"], 1);
+        assert_eq!(result["groups"][0]["indices"], json!([0, 1]));
+    }
+
+    #[test]
+    fn linreg_fits_slope_and_intercept() {
+        let result = execute(&json!({
+            "operation": "linreg",
+            "data": [
+                {"x": 1, "y": 3}, {"x": 2, "y": 5}, {"x": 3, "y": 7}, {"x": 4}, {"x": 5, "y": 11}
+            ],
+            "x": "x",
+            "y": "y"
+        }))
+        .unwrap();
+        assert_eq!(result["n"], 4);
+        assert!((result["slope"].as_f64().unwrap() - 2.0).abs() < 1e-9);
+        assert!((result["intercept"].as_f64().unwrap() - 1.0).abs() < 1e-9);
+        assert!((result["r_squared"].as_f64().unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn linreg_zero_variance_returns_null() {
+        let result = execute(&json!({
+            "operation": "linreg",
+            "data": [{"x": 2, "y": 1}, {"x": 2, "y": 5}],
+            "x": "x",
+            "y": "y"
+        }))
+        .unwrap();
+        assert!(result["slope"].is_null());
+        assert!(result["intercept"].is_null());
+        assert_eq!(result["n"], 2);
+    }
+
+    #[test]
+    fn value_counts_breaks_ties_with_order() {
+        let data = json!([
+            {"size": "large"}, {"size": "small"}, {"size": "medium"},
+            {"size": "large"}, {"size": "small"}, {"size": "medium"}, {}
+        ]);
+        let result = execute(&json!({
+            "operation": "value_counts",
+            "data": data,
+            "field": "size",
+            "order": ["small", "medium", "large"]
+        }))
+        .unwrap();
+        let values = result["values"].as_array().unwrap();
+        let sizes: Vec<&Value> = values.iter().map(|v| &v["value"]).collect();
+        assert_eq!(
+            sizes,
+            [
+                &json!("small"),
+                &json!("medium"),
+                &json!("large"),
+                &json!(null)
+            ]
+        );
+        assert_eq!(values[0]["count"], 2);
+        assert_eq!(values[3]["count"], 1);
+        assert_eq!(result["distinct"], 4);
+    }
+
+    #[test]
+    fn value_counts_top_folds_rest_into_other() {
+        let result = execute(&json!({
+            "operation": "value_counts",
+            "data": [
+                {"c": "US"}, {"c": "DE"}, {"c": "US"}, {"c": "FR"},
+                {"c": "US"}, {"c": "DE"}, {"c": "JP"}
+            ],
+            "field": "c",
+            "top": 2
+        }))
+        .unwrap();
+        assert_eq!(
+            result["values"],
+            json!([
+                {"value": "US", "count": 3},
+                {"value": "DE", "count": 2},
+                {"value": "(other)", "count": 2}
+            ])
+        );
+        assert_eq!(result["distinct"], 4);
+    }
+
+    #[test]
+    fn value_counts_sorts_by_value() {
+        let result = execute(&json!({
+            "operation": "value_counts",
+            "data": [{"r": 3}, {"r": 1}, {"r": 3}, {"r": 2}],
+            "field": "r",
+            "sort": "value"
+        }))
+        .unwrap();
+        let ratings: Vec<&Value> = result["values"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| &v["value"])
+            .collect();
+        assert_eq!(ratings, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn safe_div_rejects_zero_and_non_finite() {
+        assert_eq!(safe_div(6.0, 3.0), Some(2.0));
+        assert_eq!(safe_div(1.0, 0.0), None);
+        assert_eq!(safe_div(f64::MAX, 1e-300), None);
+        assert_eq!(safe_div(f64::NAN, 2.0), None);
+    }
+
+    #[test]
+    fn aggregate_overflowing_variance_and_range_are_null() {
+        let result = execute(&json!({
+            "operation": "aggregate",
+            "data": [f64::MAX, -f64::MAX],
+            "compute": ["variance", "range"]
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 2);
+        assert!(result.get("variance").is_some_and(Value::is_null));
+        assert!(result.get("range").is_some_and(Value::is_null));
+    }
+
+    #[test]
+    fn aggregate_mean_of_no_values_is_null() {
+        let result = execute(&json!({
+            "operation": "aggregate",
+            "data": [{"v": "n/a"}],
+            "field": "v",
+            "compute": ["mean"]
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 0);
+        assert!(result["mean"].is_null());
+    }
+
+    #[test]
+    fn slice_returns_page_with_metadata() {
+        let result = execute(&json!({
+            "operation": "slice",
+            "data": [1, 2, 3, 4, 5],
+            "offset": 1,
+            "limit": 2
+        }))
+        .unwrap();
+        assert_eq!(result["data"], json!([2, 3]));
+        assert_eq!(result["total"], 5);
+        assert_eq!(result["has_more"], true);
+
+        let result = execute(&json!({
+            "operation": "slice",
+            "data": [1, 2, 3, 4, 5],
+            "offset": -2,
+            "from_end": true
+        }))
+        .unwrap();
+        assert_eq!(result["data"], json!([4, 5]));
+        assert_eq!(result["offset"], 3);
+        assert_eq!(result["has_more"], false);
+    }
+
+    #[test]
+    fn slice_out_of_range_offsets_are_empty() {
+        for offset in [-2, 10] {
+            let result = execute(&json!({
+                "operation": "slice",
+                "data": [1, 2, 3],
+                "offset": offset
+            }))
+            .unwrap();
+            assert_eq!(result["count"], 0);
+        }
+    }
+
+    #[test]
+    fn zip_truncates_or_pads_columns() {
+        let columns = json!({"name": ["ann", "bob", "cy"], "age": [31, 42]});
+        let result = execute(&json!({"operation": "zip", "data": columns})).unwrap();
+        assert_eq!(
+            result["data"],
+            json!([{"name": "ann", "age": 31}, {"name": "bob", "age": 42}])
+        );
+
+        let result = execute(&json!({"operation": "zip", "data": columns, "pad": true})).unwrap();
+        assert_eq!(result["count"], 3);
+        assert_eq!(result["data"][2], json!({"name": "cy", "age": null}));
+    }
+
+    #[test]
+    fn infer_schema_merges_record_samples() {
+        let result = execute(&json!({
+            "operation": "infer_schema",
+            "data": [
+                {"id": 1, "name": "a", "score": 1.5, "tags": ["x"]},
+                {"id": 2, "score": 2, "tags": []}
+            ]
+        }))
+        .unwrap();
+        let items = &result["schema"]["items"];
+        assert_eq!(result["schema"]["type"], "array");
+        assert_eq!(items["properties"]["id"]["type"], "integer");
+        assert_eq!(items["properties"]["score"]["type"], "number");
+        assert_eq!(items["properties"]["tags"]["items"]["type"], "string");
+        assert_eq!(items["required"], json!(["id", "score", "tags"]));
+        assert_eq!(result["truncated"], json!([]));
+    }
+
+    #[test]
+    fn infer_schema_truncates_deep_and_wide_objects() {
+        let result = execute(&json!({
+            "operation": "infer_schema",
+            "data": {"a": {"b": {"c": 1}}, "wide": {"k1": 1, "k2": 2, "k3": 3}},
+            "max_depth": 2,
+            "max_properties": 2
+        }))
+        .unwrap();
+        let props = &result["schema"]["properties"];
+        assert_eq!(props["a"]["properties"]["b"]["type"], "object");
+        assert!(props["a"]["properties"]["b"].get("properties").is_none());
+        assert!(props["wide"].get("properties").is_none());
+        assert_eq!(result["truncated"], json!(["/a/b", "/wide"]));
+    }
+
+    #[test]
+    fn omit_removes_fields_by_name_and_pattern() {
+        let result = execute(&json!({
+            "operation": "omit",
+            "data": [
+                {"id": 1, "name": "a", "api_token": "t", "internal_flag": true, "internal_note": "x"},
+                {"id": 2, "name": "b"}
+            ],
+            "fields": ["internal_*", "*_token", "id"]
+        }))
+        .unwrap();
+        assert_eq!(result["data"], json!([{"name": "a"}, {"name": "b"}]));
+        assert_eq!(result["removed_fields"], 5);
+    }
+
+    #[test]
+    fn aggregate_group_by_with_total() {
+        let result = execute(&json!({
+            "operation": "aggregate",
+            "data": [
+                {"region": "east", "v": 10},
+                {"region": "west", "v": 5},
+                {"region": "east", "v": 20},
+                {"v": 1}
+            ],
+            "field": "v",
+            "compute": ["sum", "mean"],
+            "group_by": "region",
+            "include_total": true
+        }))
+        .unwrap();
+        assert_eq!(result["group_count"], 3);
+        let groups = result["groups"].as_array().unwrap();
+        assert_eq!(groups[0]["key"], "east");
+        assert_eq!(groups[0]["sum"], 30.0);
+        assert_eq!(groups[0]["mean"], 15.0);
+        assert_eq!(groups[1]["key"], "west");
+        assert!(groups[2]["key"].is_null());
+        assert_eq!(result["total"]["count"], 4);
+        assert_eq!(result["total"]["sum"], 36.0);
+    }
+
+    #[test]
+    fn group_normalize_methods() {
+        let data = json!([
+            {"team": "a", "pts": 10},
+            {"team": "b", "pts": 5},
+            {"team": "a", "pts": 30},
+            {"team": "b", "pts": 5},
+            {"team": "a"}
+        ]);
+        let run = |method: &str| {
+            execute(&json!({
+                "operation": "group_normalize",
+                "data": data,
+                "group_by": "team",
+                "field": "pts",
+                "method": method
+            }))
+            .unwrap()
+        };
+
+        let result = run("deviation");
+        assert_eq!(result["data"][0]["normalized"], -10.0);
+        assert_eq!(result["data"][1]["normalized"], 0.0);
+        assert!(result["data"][4]["normalized"].is_null());
+
+        let result = run("pct_of_group");
+        assert_eq!(result["data"][2]["normalized"], 75.0);
+        assert_eq!(result["data"][3]["normalized"], 50.0);
+
+        let result = run("zscore_within_group");
+        assert_eq!(result["data"][0]["normalized"], -1.0);
+        // Zero spread within group "b" makes the z-score undefined
+        assert!(result["data"][1]["normalized"].is_null());
+    }
+
+    #[test]
+    fn group_normalize_accepts_dotted_group_by() {
+        let result = execute(&json!({
+            "operation": "group_normalize",
+            "data": [
+                {"user": {"region": "east"}, "pts": 10},
+                {"user": {"region": "west"}, "pts": 4},
+                {"user": {"region": "east"}, "pts": 30}
+            ],
+            "group_by": "user.region",
+            "field": "pts"
+        }))
+        .unwrap();
+        assert_eq!(result["data"][0]["normalized"], -10.0);
+        assert_eq!(result["data"][1]["normalized"], 0.0);
+    }
+
+    #[test]
+    fn rle_collapses_consecutive_values() {
+        let result = execute(&json!({
+            "operation": "rle",
+            "data": [
+                {"s": "up"}, {"s": "up"}, {"s": "down"}, {"s": "up"}, {"s": "up"}, {"s": "up"}
+            ],
+            "field": "s"
+        }))
+        .unwrap();
+        assert_eq!(result["run_count"], 3);
+        assert_eq!(
+            result["runs"][0],
+            json!({"value": "up", "count": 2, "start_index": 0})
+        );
+        assert_eq!(
+            result["runs"][1],
+            json!({"value": "down", "count": 1, "start_index": 2})
+        );
+        assert_eq!(
+            result["longest_run"],
+            json!({"value": "up", "count": 3, "start_index": 3})
+        );
+    }
+
+    #[test]
+    fn extract_writes_first_capture_group() {
+        let result = execute(&json!({
+            "operation": "extract",
+            "data": [{"date": "2024-03-01"}, {"date": "n/a"}, {"date": 7}],
+            "field": "date",
+            "pattern": "^(\\d{4})-",
+            "output": "year"
+        }))
+        .unwrap();
+        assert_eq!(result["data"][0]["year"], "2024");
+        assert!(result["data"][1]["year"].is_null());
+        assert!(result["data"][2]["year"].is_null());
+        assert_eq!(result["matched"], 1);
+    }
+
+    #[test]
+    fn extract_rejects_invalid_pattern() {
+        let err = execute(&json!({
+            "operation": "extract",
+            "data": [],
+            "field": "x",
+            "pattern": "(unclosed"
+        }))
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Invalid pattern '(unclosed'"));
+    }
+
+    #[test]
+    fn histogram_counts_and_density() {
+        let data = json!([1, 2, 2, 3, 3, 3, 4, 5]);
+        let result = execute(&json!({
+            "operation": "histogram",
+            "data": data,
+            "bins": 4
+        }))
+        .unwrap();
+        let counts: Vec<u64> = result["bins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["count"].as_u64().unwrap())
+            .collect();
+        assert_eq!(counts, vec![1, 2, 3, 2]);
+        assert!(result.get("bin_width").is_none());
+
+        let result = execute(&json!({
+            "operation": "histogram",
+            "data": data,
+            "bins": 4,
+            "normalize": "density"
+        }))
+        .unwrap();
+        assert_eq!(result["bin_width"], 1.0);
+        let area: f64 = result["bins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["density"].as_f64().unwrap() * 1.0)
+            .sum();
+        assert!((area - 1.0).abs() < 1e-9);
+
+        let result = execute(&json!({
+            "operation": "histogram",
+            "data": data,
+            "bins": 4,
+            "normalize": "frequency"
+        }))
+        .unwrap();
+        assert_eq!(result["bins"][2]["frequency"], 0.375);
+    }
+
+    #[test]
+    fn histogram_rejects_too_many_bins_and_inverted_range() {
+        let err = execute(&json!({
+            "operation": "histogram",
+            "data": [1, 2, 3],
+            "bins": 1_000_000_000_000u64
+        }))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "'bins' must be between 1 and 10000");
+
+        let err = execute(&json!({
+            "operation": "histogram",
+            "data": [1, 2, 3],
+            "min": 5,
+            "max": 1
+        }))
+        .unwrap_err();
+        assert!(matches!(err, TransformError::InvalidValue(_)));
+    }
+
+    #[test]
+    fn histogram_rejects_unbucketable_range() {
+        let err = execute(&json!({
+            "operation": "histogram",
+            "data": [-f64::MAX, f64::MAX]
+        }))
+        .unwrap_err();
+        assert!(matches!(err, TransformError::InvalidValue(_)));
+    }
+
+    #[test]
+    fn score_combines_weighted_fields() {
+        let data = json!([
+            {"q": 10, "speed": 100},
+            {"q": 0, "speed": 300},
+            {"q": 5}
+        ]);
+        let result = execute(&json!({
+            "operation": "score",
+            "data": data,
+            "weights": {"q": 0.5, "speed": 0.5},
+            "normalize": true
+        }))
+        .unwrap();
+        assert_eq!(result["data"][0]["score"], 0.5);
+        assert_eq!(result["data"][1]["score"], 0.5);
+        assert_eq!(result["data"][2]["score"], 0.25);
+
+        let result = execute(&json!({
+            "operation": "score",
+            "data": data,
+            "weights": {"q": 2, "speed": 1},
+            "missing": "exclude",
+            "output": "rank_score"
+        }))
+        .unwrap();
+        assert_eq!(result["data"][0]["rank_score"], 120.0);
+        assert!(result["data"][2]["rank_score"].is_null());
+    }
+
+    #[test]
+    fn cond_aggregate_only_uses_matching_records() {
+        let result = execute(&json!({
+            "operation": "cond_aggregate",
+            "data": [
+                {"qty": 5, "price": 10},
+                {"qty": 1, "price": 100},
+                {"qty": 8, "price": 30}
+            ],
+            "where": {"field": "qty", "op": ">=", "value": 5},
+            "field": "price",
+            "compute": ["sum", "mean"]
+        }))
+        .unwrap();
+        assert_eq!(result["matched"], 2);
+        assert_eq!(result["sum"], 40.0);
+        assert_eq!(result["mean"], 20.0);
+        assert_eq!(result["original_count"], 3);
+    }
+
+    #[test]
+    fn cache_key_is_stable_across_key_order() {
+        let a = execute(&json!({
+            "operation": "stats",
+            "data": [{"v": 1}, {"v": 2}],
+            "field": "v",
+            "cache_key": true
+        }))
+        .unwrap();
+        let b = execute(&serde_json::from_str::<Value>(
+            r#"{"cache_key": true, "field": "v", "data": [{"v": 1}, {"v": 2}], "operation": "stats"}"#,
+        ).unwrap())
+        .unwrap();
+        let c = execute(&json!({
+            "operation": "stats",
+            "data": [{"v": 1}, {"v": 3}],
+            "field": "v",
+            "cache_key": true
+        }))
+        .unwrap();
+        assert_eq!(a["cache_key"].as_str().unwrap().len(), 16);
+        assert_eq!(a["cache_key"], b["cache_key"]);
+        assert_ne!(a["cache_key"], c["cache_key"]);
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a_64(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_64(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn interpolate_linear_fills_interior_gaps() {
+        let data = json!([{"v": null}, {"v": 10}, {}, {"v": null}, {"v": 40}, {"t": 1}]);
+        let result = execute(&json!({
+            "operation": "interpolate",
+            "data": data,
+            "field": "v"
+        }))
+        .unwrap();
+        let rows = result["data"].as_array().unwrap();
+        assert!(rows[0]["v"].is_null());
+        assert_eq!(rows[2]["v"], 20.0);
+        assert_eq!(rows[3]["v"], 30.0);
+        assert!(rows[5].get("v").is_none());
+        assert_eq!(result["filled"], 2);
+
+        let result = execute(&json!({
+            "operation": "interpolate",
+            "data": data,
+            "field": "v",
+            "method": "forward",
+            "fill_edges": true
+        }))
+        .unwrap();
+        let filled: Vec<f64> = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["v"].as_f64().unwrap())
+            .collect();
+        assert_eq!(filled, vec![10.0, 10.0, 10.0, 10.0, 40.0, 40.0]);
+    }
+
+    #[test]
+    fn detect_jumps_flags_large_changes() {
+        let data = json!([{"v": 100}, {"v": 105}, {"v": 150}, {}, {"v": 10}, {"v": 0}, {"v": 4}]);
+        let result = execute(&json!({
+            "operation": "detect_jumps",
+            "data": data,
+            "field": "v",
+            "threshold": 20
+        }))
+        .unwrap();
+        assert_eq!(result["jump_count"], 1);
+        assert_eq!(result["jumps"][0]["index"], 2);
+        assert_eq!(result["jumps"][0]["delta"], 45.0);
+        assert_eq!(result["pairs_checked"], 4);
+
+        let result = execute(&json!({
+            "operation": "detect_jumps",
+            "data": data,
+            "field": "v",
+            "threshold": 50,
+            "mode": "percent"
+        }))
+        .unwrap();
+        let indices: Vec<u64> = result["jumps"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|j| j["index"].as_u64().unwrap())
+            .collect();
+        // 10 → 0 is -100%; 0 → 4 has no defined percentage
+        assert_eq!(indices, vec![5]);
+    }
+
+    #[test]
+    fn object_keys_keep_a_stable_order() {
+        let input = json!({
+            "operation": "sort",
+            "data": [{"zeta": 2, "alpha": 1}, {"zeta": 1, "alpha": 2}],
+            "field": "zeta"
+        });
+        let first = serde_json::to_string(&execute(&input).unwrap()).unwrap();
+        assert!(first.starts_with(r#"{"data":[{"zeta":1,"alpha":2}"#));
+        assert_eq!(
+            first,
+            serde_json::to_string(&execute(&input).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn weighted_sample_is_seeded_and_skips_non_positive_weights() {
+        let data = json!([
+            {"id": 1, "w": 5},
+            {"id": 2, "w": 0},
+            {"id": 3, "w": -1},
+            {"id": 4},
+            {"id": 5, "w": 1},
+            {"id": 6, "w": 2}
+        ]);
+        let input = json!({
+            "operation": "weighted_sample",
+            "data": data,
+            "n": 10,
+            "weight_field": "w",
+            "seed": 42
+        });
+        let result = execute(&input).unwrap();
+        assert_eq!(result["eligible"], 3);
+        // Without replacement the sample is capped at the eligible records
+        let mut ids: Vec<i64> = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].as_i64().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 5, 6]);
+        assert_eq!(execute(&input).unwrap(), result);
+
+        let mut with_replacement = input.clone();
+        with_replacement["replace"] = json!(true);
+        with_replacement["n"] = json!(1000);
+        let result = execute(&with_replacement).unwrap();
+        assert_eq!(result["count"], 1000);
+        let heavy = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|r| r["id"] == 1)
+            .count();
+        // Weight 5 of 8 → expect ~625 draws
+        assert!((550..700).contains(&heavy), "{heavy}");
+    }
+
+    #[test]
+    fn weighted_sample_rejects_oversized_n() {
+        let err = execute(&json!({
+            "operation": "weighted_sample",
+            "data": [{"w": 1}],
+            "weight_field": "w",
+            "n": 1_000_000_000u64,
+            "replace": true
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err,
+            TransformError::InvalidValue("'n' must be at most 100000".into())
+        );
+    }
+
+    #[test]
+    fn filter_fuzzy_matches_typos_and_annotates_scores() {
+        let data = json!([
+            {"name": "Seattle"},
+            {"name": "seatle"},
+            {"name": "Portland"},
+            {"name": 42},
+            {}
+        ]);
+        let result = execute(&json!({
+            "operation": "filter",
+            "data": data,
+            "where": {"field": "name", "op": "fuzzy", "value": "Seattle", "max_distance": 1},
+            "annotate": true,
+            "explain": true
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 2);
+        assert_eq!(result["data"][0]["match_score"], 1.0);
+        let partial = result["data"][1]["match_score"].as_f64().unwrap();
+        assert!((partial - 6.0 / 7.0).abs() < 1e-9);
+        assert_eq!(result["explain"]["missing_field"], 1);
+        assert_eq!(result["explain"]["type_mismatch"], 1);
+
+        let strict = execute(&json!({
+            "operation": "filter",
+            "data": data,
+            "where": {"field": "name", "op": "fuzzy", "value": "Seattle", "min_similarity": 0.9}
+        }))
+        .unwrap();
+        assert_eq!(strict["count"], 1);
+        assert!(strict["data"][0].get("match_score").is_none());
+    }
+
+    #[test]
+    fn sparkline_returns_flat_counts() {
+        let result = execute(&json!({
+            "operation": "sparkline",
+            "data": [1, 2, 2, 3, 3, 3, 4, 5],
+            "buckets": 4
+        }))
+        .unwrap();
+        assert_eq!(
+            result,
+            json!({"counts": [1, 2, 3, 2], "min": 1.0, "max": 5.0})
+        );
+
+        let empty = execute(&json!({"operation": "sparkline", "data": [], "buckets": 3})).unwrap();
+        assert_eq!(empty["counts"], json!([0, 0, 0]));
+        assert!(empty["min"].is_null());
+    }
+
+    #[test]
+    fn sparkline_rejects_too_many_buckets() {
+        let err = execute(&json!({
+            "operation": "sparkline",
+            "data": [1, 2, 3],
+            "buckets": 1_000_000_000_000u64
+        }))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "'buckets' must be between 1 and 10000");
+    }
+
+    #[test]
+    fn crosstab_counts_combinations() {
+        let data = json!([
+            {"region": "east", "tier": "gold"},
+            {"region": "east", "tier": "free"},
+            {"region": "west", "tier": "free"},
+            {"region": "east", "tier": "free"},
+            {"tier": "gold"}
+        ]);
+        let result = execute(&json!({
+            "operation": "crosstab",
+            "data": data,
+            "rows": "region",
+            "cols": "tier",
+            "normalize": "row"
+        }))
+        .unwrap();
+        assert_eq!(result["row_labels"], json!(["east", "west", null]));
+        assert_eq!(result["col_labels"], json!(["gold", "free"]));
+        assert_eq!(result["counts"], json!([[1, 2], [0, 1], [1, 0]]));
+        assert_eq!(result["col_totals"], json!([2, 3]));
+        assert_eq!(result["total"], 5);
+        let east_free = result["proportions"][0][1].as_f64().unwrap();
+        assert!((east_free - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn crosstab_accepts_dotted_fields() {
+        let result = execute(&json!({
+            "operation": "crosstab",
+            "data": [
+                {"user": {"region": "east"}, "plan": {"tier": "gold"}},
+                {"user": {"region": "east"}, "plan": {"tier": "free"}},
+                {"user": {"region": "west"}, "plan": {"tier": "free"}}
+            ],
+            "rows": "user.region",
+            "cols": "plan.tier"
+        }))
+        .unwrap();
+        assert_eq!(result["row_labels"], json!(["east", "west"]));
+        assert_eq!(result["col_labels"], json!(["gold", "free"]));
+        assert_eq!(result["counts"], json!([[1, 1], [0, 1]]));
+    }
+
+    #[test]
+    fn stringified_data_is_parsed() {
+        let result = execute(&json!({
+            "operation": "stats",
+            "data": "[{\"value\": 10}, {\"value\": 20}]",
+            "field": "value"
+        }))
+        .unwrap();
+        assert_eq!(result["sum"], 30.0);
+
+        // Strings that aren't a JSON array/object still fail as before
+        let err = execute(&json!({"operation": "stats", "data": "42"})).unwrap_err();
+        assert_eq!(
+            err,
+            TransformError::WrongType("'data' must be an array".into())
+        );
+    }
+
+    #[test]
+    fn checksum_changes_only_with_selected_fields() {
+        let result = execute(&json!({
+            "operation": "checksum",
+            "data": [
+                {"id": 1, "name": "a", "seen": 1},
+                {"seen": 2, "name": "a", "id": 1},
+                {"id": 1, "name": "b", "seen": 1}
+            ],
+            "fields": ["id", "name"]
+        }))
+        .unwrap();
+        let sums: Vec<&str> = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["checksum"].as_str().unwrap())
+            .collect();
+        assert_eq!(sums[0].len(), 16);
+        assert_eq!(sums[0], sums[1]);
+        assert_ne!(sums[0], sums[2]);
+    }
+
+    #[test]
+    fn allowlist_limits_enabled_operations() {
+        set_enabled_operations(Some(vec!["stats".into(), "filter".into()])).unwrap();
+        assert_eq!(supported_operations(), vec!["stats", "filter"]);
+        let err = execute(&json!({"operation": "sort", "data": [], "field": "x"})).unwrap_err();
+        assert_eq!(err.to_string(), "operation 'sort' is not enabled");
+        assert!(execute(&json!({"operation": "stats", "data": [1]})).is_ok());
+
+        assert!(set_enabled_operations(Some(vec!["nope".into()])).is_err());
+        set_enabled_operations(None).unwrap();
+        assert_eq!(supported_operations().len(), BUILTINS.len());
+    }
+
+    #[test]
+    fn where_clause_scopes_row_operations() {
+        let result = execute(&json!({
+            "operation": "format_numbers",
+            "data": [{"amount": 50}, {"amount": 1500}, {"amount": 2500}],
+            "fields": ["amount"],
+            "decimals": 0,
+            "where": {"field": "amount", "op": ">", "value": 100}
+        }))
+        .unwrap();
+        assert_eq!(result["count"], 3);
+        assert_eq!(result["updated"], 2);
+        assert!(result["data"][0].get("amount_formatted").is_none());
+        assert_eq!(result["data"][1]["amount_formatted"], "1,500");
+        assert_eq!(result["data"][2]["amount_formatted"], "2,500");
+    }
+
+    #[test]
+    fn where_clause_scopes_zscore() {
+        let result = execute(&json!({
+            "operation": "zscore",
+            "data": [{"v": 1}, {"v": 10}, {"v": 20}],
+            "field": "v",
+            "where": {"field": "v", "op": ">", "value": 5}
+        }))
+        .unwrap();
+        assert_eq!(result["updated"], 2);
+        assert_eq!(result["mean"], 15.0);
+        assert_eq!(result["data"][0], json!({"v": 1}));
+        assert_eq!(result["data"][1]["zscore"], -1.0);
+        assert_eq!(result["data"][2]["zscore"], 1.0);
+    }
+
+    #[test]
+    fn where_clause_scopes_fillna() {
+        let result = execute(&json!({
+            "operation": "fillna",
+            "data": [{"g": "a", "v": 2}, {"g": "a", "v": 4}, {"g": "a"}, {"g": "b"}, {"g": "b", "v": 100}],
+            "fill": "mean",
+            "fields": ["v"],
+            "where": {"field": "g", "op": "==", "value": "a"}
+        }))
+        .unwrap();
+        assert_eq!(result["updated"], 3);
+        // The mean comes from the matching records only
+        assert_eq!(result["data"][2]["v"], 3.0);
+        assert_eq!(result["data"][3], json!({"g": "b"}));
+    }
+
+    #[test]
+    fn where_clause_scopes_normalize_statistics() {
+        let result = execute(&json!({
+            "operation": "normalize",
+            "data": [{"a": 1}, {"a": 3}, {"a": 5}],
+            "field": "a",
+            "where": {"field": "a", "op": ">", "value": 2}
+        }))
+        .unwrap();
+        assert_eq!(result["updated"], 2);
+        assert_eq!(result["data"][0], json!({"a": 1}));
+        // Min and max come from the matching records only
+        assert_eq!(result["data"][1]["normalized"], 0.0);
+        assert_eq!(result["data"][2]["normalized"], 1.0);
+    }
+
+    #[test]
+    fn where_clause_scopes_map() {
+        let result = execute(&json!({
+            "operation": "map",
+            "data": [{"a": 1}, {"a": 3}],
+            "rules": [{"target": "b", "expr": {"op": "*", "args": ["a", 2]}}],
+            "where": {"field": "a", "op": ">", "value": 2}
+        }))
+        .unwrap();
+        assert_eq!(result["updated"], 1);
+        assert_eq!(result["data"][0], json!({"a": 1}));
+        assert_eq!(result["data"][1]["b"], 6.0);
+    }
+
+    #[test]
+    fn unpivot_melts_value_fields() {
+        let result = execute(&json!({
+            "operation": "unpivot",
+            "data": [{"region": "west", "q1": 10, "q2": 20}, {"region": "east", "q1": 5}],
+            "id_fields": ["region"],
+            "variable_name": "quarter"
+        }))
+        .unwrap();
+        assert_eq!(
+            result["data"],
+            json!([
+                {"region": "west", "quarter": "q1", "value": 10},
+                {"region": "west", "quarter": "q2", "value": 20},
+                {"region": "east", "quarter": "q1", "value": 5}
+            ])
+        );
+
+        let explicit = execute(&json!({
+            "operation": "unpivot",
+            "data": [{"region": "east", "q1": 5}],
+            "id_fields": ["region"],
+            "value_fields": ["q1", "q2"]
+        }))
+        .unwrap();
+        assert_eq!(explicit["count"], 2);
+        assert!(explicit["data"][1]["value"].is_null());
+    }
+
+    #[test]
+    fn csv_parse_maps_null_markers_and_types() {
+        let result = execute(&json!({
+            "operation": "csv_parse",
+            "data": "name,score,note\r\nann,10,\nbob,NA,\"NA\"\n\"c, d\",2.5,\"say \"\"hi\"\"\"\n",
+            "null_values": ["", "NA"]
+        }))
+        .unwrap();
+        assert_eq!(result["columns"], json!(["name", "score", "note"]));
+        assert_eq!(
+            result["data"],
+            json!([
+                {"name": "ann", "score": 10, "note": null},
+                {"name": "bob", "score": null, "note": "NA"},
+                {"name": "c, d", "score": 2.5, "note": "say \"hi\""}
+            ])
+        );
+    }
+
+    #[test]
+    fn csv_format_round_trips_nulls() {
+        let data = json!([
+            {"name": "ann", "score": 10, "note": null},
+            {"name": "NULL", "score": 2.5}
+        ]);
+        let formatted = execute(&json!({
+            "operation": "csv_format",
+            "data": data,
+            "null_output": "NULL"
+        }))
+        .unwrap();
+        assert_eq!(
+            formatted["data"],
+            "name,score,note\nann,10,NULL\n\"NULL\",2.5,NULL\n"
+        );
+
+        let parsed = execute(&json!({
+            "operation": "csv_parse",
+            "data": formatted["data"],
+            "null_values": ["NULL"]
+        }))
+        .unwrap();
+        assert_eq!(parsed["data"][0], data[0]);
+        assert_eq!(parsed["data"][1]["name"], "NULL");
+        assert!(parsed["data"][1]["note"].is_null());
+    }
+
+    #[test]
+    fn gini_measures_inequality_and_skips_negatives() {
+        let equal = execute(&json!({"operation": "gini", "data": [5, 5, 5, 5]})).unwrap();
+        assert!(equal["gini"].as_f64().unwrap().abs() < 1e-12);
+
+        let result = execute(&json!({
+            "operation": "gini",
+            "data": [{"income": 0}, {"income": 0}, {"income": 0}, {"income": 10}, {"income": -3}],
+            "field": "income",
+            "lorenz": true
+        }))
+        .unwrap();
+        assert!((result["gini"].as_f64().unwrap() - 0.75).abs() < 1e-12);
+        assert_eq!(result["negative_skipped"], 1);
+        assert!(result["note"].is_string());
+        let lorenz = result["lorenz"].as_array().unwrap();
+        assert_eq!(lorenz.len(), 5);
+        assert_eq!(
+            lorenz[3],
+            json!({"population_share": 0.75, "value_share": 0.0})
+        );
+        assert_eq!(
+            lorenz[4],
+            json!({"population_share": 1.0, "value_share": 1.0})
+        );
+    }
+
+    #[test]
+    fn sort_with_declared_type() {
+        let data = json!([
+            {"v": "10"},
+            {"v": "9"},
+            {"v": "n/a"},
+            {"v": "2024-01-01T00:30:00+01:00"},
+            {"v": 2.5}
+        ]);
+        let sorted = |key_type: &str, descending: bool| -> Vec<Value> {
+            let result = execute(&json!({
+                "operation": "sort",
+                "data": data,
+                "field": "v",
+                "type": key_type,
+                "descending": descending
+            }))
+            .unwrap();
+            result["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["v"].clone())
+                .collect()
+        };
+        assert_eq!(
+            sorted("number", false)[..3],
+            [json!(2.5), json!("9"), json!("10")]
+        );
+        assert_eq!(
+            sorted("number", true)[..3],
+            [json!("10"), json!("9"), json!(2.5)]
+        );
+        // Strings compare lexically, so "10" < "9"
+        assert_eq!(sorted("string", false)[..2], [json!("10"), json!(2.5)]);
+
+        let dates = execute(&json!({
+            "operation": "sort",
+            "data": [
+                {"at": "2024-01-01T00:30:00+01:00"},
+                {"at": "2023-12-31T23:45:00Z"},
+                {"at": "2024-01-01"},
+                {"at": "yesterday"}
+            ],
+            "field": "at",
+            "type": "date"
+        }))
+        .unwrap();
+        let order: Vec<&str> = dates["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["at"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "2024-01-01T00:30:00+01:00",
+                "2023-12-31T23:45:00Z",
+                "2024-01-01",
+                "yesterday"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_infers_strings_and_places_nulls() {
+        let data = json!([
+            {"name": "carol"},
+            {"name": null},
+            {"name": "alice"},
+            {},
+            {"name": "bob"}
+        ]);
+        let names = |extra: Value| -> Vec<Value> {
+            let mut input = json!({"operation": "sort", "data": data, "field": "name"});
+            input
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            let result = execute(&input).unwrap();
+            result["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["name"].clone())
+                .collect()
+        };
+        assert_eq!(
+            names(json!({})),
+            [
+                json!("alice"),
+                json!("bob"),
+                json!("carol"),
+                Value::Null,
+                Value::Null
+            ]
+        );
+        assert_eq!(
+            names(json!({"descending": true, "nulls": "first"})),
+            [
+                Value::Null,
+                Value::Null,
+                json!("carol"),
+                json!("bob"),
+                json!("alice")
+            ]
+        );
+
+        // Missing fields no longer interleave with real zeros
+        let mixed = execute(&json!({
+            "operation": "sort",
+            "data": [{"v": 1}, {}, {"v": 0}, {"v": -1}],
+            "field": "v"
+        }))
+        .unwrap();
+        assert_eq!(mixed["data"], json!([{"v": -1}, {"v": 0}, {"v": 1}, {}]));
+    }
+
+    #[test]
+    fn sort_by_multiple_keys() {
+        let result = execute(&json!({
+            "operation": "sort",
+            "data": [
+                {"customer": "b", "amount": 5, "id": 1},
+                {"customer": "a", "amount": 10, "id": 2},
+                {"customer": "b", "amount": 20, "id": 3},
+                {"customer": "a", "amount": 10, "id": 4},
+                {"customer": "a", "amount": 30, "id": 5}
+            ],
+            "by": [
+                {"field": "customer"},
+                {"field": "amount", "descending": true}
+            ]
+        }))
+        .unwrap();
+        let ids: Vec<i64> = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].as_i64().unwrap())
+            .collect();
+        // Equal keys keep their input order
+        assert_eq!(ids, vec![5, 2, 4, 3, 1]);
+
+        let err = execute(&json!({"operation": "sort", "data": [], "by": [{"descending": true}]}));
+        assert_eq!(
+            err.unwrap_err(),
+            TransformError::MissingField("'field' is required for sort operation".into())
+        );
+    }
+
+    #[test]
+    fn filter_and_sort_paginate() {
+        let data = json!([{"v": 5}, {"v": 1}, {"v": 4}, {"v": 2}, {"v": 3}]);
+        let page = execute(&json!({
+            "operation": "sort",
+            "data": data,
+            "field": "v",
+            "offset": 1,
+            "limit": 2
+        }))
+        .unwrap();
+        assert_eq!(page["data"], json!([{"v": 2}, {"v": 3}]));
+        assert_eq!(page["count"], 2);
+        assert_eq!(page["total"], 5);
+        assert_eq!(page["has_more"], true);
+
+        let filtered = execute(&json!({
+            "operation": "filter",
+            "data": data,
+            "where": {"field": "v", "op": ">", "value": 1},
+            "limit": 3
+        }))
+        .unwrap();
+        assert_eq!(filtered["data"], json!([{"v": 5}, {"v": 4}, {"v": 2}]));
+        assert_eq!(filtered["total"], 4);
+        assert_eq!(filtered["original_count"], 5);
+
+        let negative = execute(&json!({
+            "operation": "sort",
+            "data": data,
+            "field": "v",
+            "offset": -2
+        }))
+        .unwrap();
+        assert_eq!(negative["count"], 0);
+        assert_eq!(negative["has_more"], false);
+    }
+
+    #[test]
+    fn rfc3339_parses_to_epoch_seconds() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(parse_rfc3339("2000-03-01"), Some(951_868_800.0));
+        assert_eq!(parse_rfc3339("1970-01-01T01:00:00.25+01:00"), Some(0.25));
+        assert_eq!(parse_rfc3339("2023-02-29"), None);
+        assert_eq!(parse_rfc3339("2024-01-01T00:00:00"), None);
+    }
+
+    #[test]
+    fn partition_splits_into_complementary_groups() {
+        let result = execute(&json!({
+            "operation": "partition",
+            "data": [{"score": 80}, {"score": 40}, {"score": 65}, {}],
+            "where": {"field": "score", "op": ">=", "value": 60}
+        }))
+        .unwrap();
+        assert_eq!(result["matched"], json!([{"score": 80}, {"score": 65}]));
+        assert_eq!(result["unmatched"], json!([{"score": 40}, {}]));
+        assert_eq!(result["matched_count"], 2);
+        assert_eq!(result["unmatched_count"], 2);
+    }
+
+    #[test]
+    fn object_diff_reports_nested_paths() {
+        let result = execute(&json!({
+            "operation": "object_diff",
+            "a": {"name": "Ann", "age": 30, "address": {"city": "Oslo", "zip": "0150"}, "tags": [1]},
+            "b": {"name": "Ann", "age": 31, "address": {"city": "Bergen"}, "tags": [1], "email": "a@x"}
+        }))
+        .unwrap();
+        assert_eq!(
+            result["changed"],
+            json!([
+                {"path": "age", "old": 30, "new": 31},
+                {"path": "address.city", "old": "Oslo", "new": "Bergen"}
+            ])
+        );
+        assert_eq!(
+            result["removed"],
+            json!([{"path": "address.zip", "value": "0150"}])
+        );
+        assert_eq!(result["added"], json!([{"path": "email", "value": "a@x"}]));
+        assert_eq!(result["identical"], false);
+    }
+
+    #[test]
+    fn zscore_standardizes_each_record() {
+        let result = execute(&json!({
+            "operation": "zscore",
+            "data": [{"v": 2}, {"v": 4}, {"v": 6}, {"v": "n/a"}],
+            "field": "v"
+        }))
+        .unwrap();
+        let sd = (8.0f64 / 3.0).sqrt();
+        assert_eq!(result["mean"], 4.0);
+        assert!((result["data"][0]["zscore"].as_f64().unwrap() + 2.0 / sd).abs() < 1e-12);
+        assert_eq!(result["data"][1]["zscore"], 0.0);
+        assert!(result["data"][3]["zscore"].is_null());
+
+        let flat = json!([{"v": 3}, {"v": 3}]);
+        let zero = execute(&json!({"operation": "zscore", "data": flat, "field": "v"})).unwrap();
+        assert_eq!(zero["data"][0]["zscore"], 0.0);
+        let null = execute(&json!({
+            "operation": "zscore",
+            "data": flat,
+            "field": "v",
+            "zero_variance": "null"
+        }))
+        .unwrap();
+        assert!(null["data"][0]["zscore"].is_null());
+    }
+
+    #[test]
+    fn registered_operations_are_dispatched() {
+        fn echo(input: &Value) -> Result<Value, TransformError> {
+            Ok(json!({"echo": input["data"]}))
+        }
+        register_operation("echo", echo).unwrap();
+        let result = execute(&json!({"operation": "echo", "data": [1, 2]})).unwrap();
+        assert_eq!(result, json!({"echo": [1, 2]}));
+        assert_eq!(supported_operations().last().unwrap(), "echo");
+
+        let err = register_operation("stats", echo).unwrap_err();
+        assert_eq!(err, "Operation 'stats' is already registered");
+    }
+
+    #[test]
+    fn group_by_rolls_up_each_key() {
+        let result = execute(&json!({
+            "operation": "group_by",
+            "data": [
+                {"region": "us", "sales": 10},
+                {"region": true, "sales": 5},
+                {"region": "us", "sales": 30},
+                {"sales": 7}
+            ],
+            "by": "region",
+            "field": "sales",
+            "compute": ["sum", "mean"]
+        }))
+        .unwrap();
+        assert_eq!(
+            result["groups"],
+            json!([
+                {"key": "us", "count": 2, "sum": 40.0, "mean": 20.0},
+                {"key": true, "count": 1, "sum": 5.0, "mean": 5.0},
+                {"key": null, "count": 1, "sum": 7.0, "mean": 7.0}
+            ])
+        );
+        assert_eq!(result["group_count"], 3);
+    }
+
+    #[test]
+    fn group_by_matches_aggregate_group_by() {
+        let options = json!({
+            "data": [
+                {"region": "us", "sales": 10, "w": 1},
+                {"region": "eu", "sales": -1, "w": 2},
+                {"region": "us", "sales": 30, "w": 3}
+            ],
+            "field": "sales",
+            "compute": ["sum", "mean"],
+            "weight": "w",
+            "exclude_values": [-1],
+            "include_total": true
+        });
+        let mut grouped = options.clone();
+        grouped["operation"] = json!("group_by");
+        grouped["by"] = json!("region");
+        let mut aggregated = options;
+        aggregated["operation"] = json!("aggregate");
+        aggregated["group_by"] = json!("region");
+
+        let result = execute(&grouped).unwrap();
+        assert_eq!(result, execute(&aggregated).unwrap());
+        assert_eq!(result["groups"][0]["weighted_mean"], 25.0);
+        assert_eq!(result["groups"][1]["excluded_count"], 1);
+        assert_eq!(result["total"]["sum"], 40.0);
+    }
+
+    #[test]
+    fn filter_nested_and_or_clauses() {
+        let data = json!([
+            {"x": 5, "y": 1},
+            {"x": 15, "y": 1},
+            {"x": 15, "y": 0},
+            {"x": 1, "y": 9}
+        ]);
+        let filter = |clause: Value| {
+            let result =
+                execute(&json!({"operation": "filter", "data": data, "where": clause})).unwrap();
+            result["count"].as_u64().unwrap()
+        };
+        let x_gt_10 = json!({"field": "x", "op": ">", "value": 10});
+        let y_eq_1 = json!({"field": "y", "op": "==", "value": 1});
+        let y_gt_5 = json!({"field": "y", "op": ">", "value": 5});
+
+        assert_eq!(filter(json!({"and": [x_gt_10, y_eq_1]})), 1);
+        assert_eq!(filter(json!({"or": [x_gt_10, y_gt_5]})), 3);
+        assert_eq!(
+            filter(json!({"or": [{"and": [x_gt_10, y_eq_1]}, y_gt_5]})),
+            2
+        );
+        assert_eq!(filter(json!({"and": []})), 4);
+        assert_eq!(filter(json!({"or": []})), 0);
+    }
+
+    #[test]
+    fn distinct_keeps_first_occurrence() {
+        let data = json!([
+            {"id": 1, "v": "a"},
+            {"v": "a", "id": 1},
+            {"id": 2, "v": "a"},
+            {"id": 1, "v": "b"}
+        ]);
+        let whole = execute(&json!({"operation": "distinct", "data": data})).unwrap();
+        assert_eq!(whole["count"], 3);
+        assert_eq!(whole["removed"], 1);
+
+        let by_id =
+            execute(&json!({"operation": "distinct", "data": data, "fields": ["id"]})).unwrap();
+        assert_eq!(
+            by_id["data"],
+            json!([{"id": 1, "v": "a"}, {"id": 2, "v": "a"}])
+        );
+        assert_eq!(by_id["removed"], 2);
+    }
+
+    #[test]
+    fn select_projects_fields_and_paths() {
+        let data = json!([
+            {"id": 1, "user": {"name": "ann", "age": 30}, "meta_a": 1, "meta_b": 2, "x": 0},
+            {"id": 2, "user": {"age": 40}}
+        ]);
+        let flat = execute(&json!({
+            "operation": "select",
+            "data": data,
+            "fields": ["user.name", "id", "meta_*"]
+        }))
+        .unwrap();
+        assert_eq!(
+            flat["data"],
+            json!([
+                {"user.name": "ann", "id": 1, "meta_a": 1, "meta_b": 2},
+                {"id": 2}
+            ])
+        );
+        let first = serde_json::to_string(&flat["data"][0]).unwrap();
+        assert!(first.starts_with(r#"{"user.name""#));
+
+        let nested = execute(&json!({
+            "operation": "select",
+            "data": data,
+            "fields": ["id", "user.name"],
+            "flatten": false
+        }))
+        .unwrap();
+        assert_eq!(nested["data"][0], json!({"id": 1, "user": {"name": "ann"}}));
+    }
+
+    #[test]
+    fn rename_maps_keys_and_rejects_collisions() {
+        let result = execute(&json!({
+            "operation": "rename",
+            "data": [{"id": 1, "amt": 5}, {"amt": 7, "note": "x"}],
+            "map": {"amt": "amount"}
+        }))
+        .unwrap();
+        assert_eq!(
+            result["data"],
+            json!([{"id": 1, "amount": 5}, {"amount": 7, "note": "x"}])
+        );
+        assert_eq!(result["renamed"], 2);
+
+        let swapped = execute(&json!({
+            "operation": "rename",
+            "data": [{"a": 1, "b": 2}],
+            "map": {"a": "b", "b": "a"}
+        }))
+        .unwrap();
+        assert_eq!(swapped["data"][0], json!({"b": 1, "a": 2}));
+
+        let err = execute(&json!({
+            "operation": "rename",
+            "data": [{"amount": 1, "amt": 5}],
+            "map": {"amt": "amount"}
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Record 0: renaming 'amt' to 'amount' would overwrite an existing field"
+        );
+    }
+
+    #[test]
+    fn errors_carry_a_kind_and_display_the_message() {
+        let missing = execute(&json!({"operation": "stats"})).unwrap_err();
+        assert_eq!(
+            missing,
+            TransformError::MissingField("'data' must be an array".into())
+        );
+        let unknown = execute(&json!({"operation": "explode", "data": []})).unwrap_err();
+        assert!(matches!(unknown, TransformError::UnknownOperation(_)));
+        assert!(unknown
+            .to_string()
+            .starts_with("Unknown operation: explode. Use: stats"));
+        let invalid = execute(&json!({"operation": "stats", "data": [1], "stat": "size"}));
+        assert_eq!(
+            invalid.unwrap_err(),
+            TransformError::InvalidValue("Unknown stat: size. Use: value, length".into())
+        );
     }
 
-    let count = values.len() as f64;
-    let sum: f64 = values.iter().sum();
-    let mean = sum / count;
-    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
-    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-    let variance: f64 = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
-    let std_dev = variance.sqrt();
+    #[test]
+    fn aggregate_range_variance_sum_sq_and_unknown_ops() {
+        let result = execute(&json!({
+            "operation": "aggregate",
+            "data": [{"v": 2}, {"v": 4}, {"v": 6}],
+            "field": "v",
+            "compute": ["range", "variance", "sum_sq", "average"]
+        }))
+        .unwrap();
+        assert_eq!(result["range"], 4.0);
+        assert!((result["variance"].as_f64().unwrap() - 8.0 / 3.0).abs() < 1e-9);
+        assert_eq!(result["sum_sq"], 56.0);
+        assert_eq!(
+            result["errors"],
+            json!(["Unknown compute operation: average. Use: sum, mean, min, max, range, variance, sum_sq"])
+        );
+    }
 
-    Ok(json!({
-        "count": count as i64,
-        "sum": sum,
-        "mean": mean,
-        "min": min,
-        "max": max,
-        "std_dev": std_dev,
-    }))
-}
+    #[test]
+    fn weighted_mean_in_stats_and_aggregate() {
+        let data = json!([
+            {"price": 10, "qty": 1, "side": "buy"},
+            {"price": 20, "qty": 3, "side": "buy"},
+            {"price": 50, "side": "sell"},
+            {"price": 40, "qty": 0, "side": "sell"}
+        ]);
+        let stats = execute(&json!({
+            "operation": "stats",
+            "data": data,
+            "field": "price",
+            "weight": "qty"
+        }))
+        .unwrap();
+        assert_eq!(stats["weighted_mean"], 17.5);
+        assert_eq!(stats["mean"], 30.0);
 
-fn filter_data(input: &Value) -> Result<Value, String> {
-    let data = input
-        .get("data")
-        .and_then(|v| v.as_array())
-        .ok_or("'data' must be an array")?;
+        let grouped = execute(&json!({
+            "operation": "aggregate",
+            "data": data,
+            "field": "price",
+            "compute": ["mean"],
+            "group_by": "side",
+            "weight": "qty"
+        }))
+        .unwrap();
+        assert_eq!(grouped["groups"][0]["weighted_mean"], 17.5);
+        assert_eq!(grouped["groups"][1]["weighted_mean"], Value::Null);
+    }
 
-    let where_clause = input
-        .get("where")
-        .ok_or("'where' clause is required for filter operation")?;
+    #[test]
+    fn aggregate_empty_values_are_null() {
+        let result = execute(&json!({
+            "operation": "aggregate",
+            "data": [{"v": "n/a"}, {}],
+            "field": "v",
+            "compute": ["sum", "mean", "min", "max"]
+        }))
+        .unwrap();
+        assert_eq!(
+            result,
+            json!({"count": 0, "sum": null, "mean": null, "min": null, "max": null})
+        );
+    }
 
-    let field = where_clause
-        .get("field")
-        .and_then(|v| v.as_str())
-        .ok_or("'where.field' is required")?;
-    let op = where_clause
-        .get("op")
-        .and_then(|v| v.as_str())
-        .ok_or("'where.op' is required")?;
-    let threshold = where_clause
-        .get("value")
-        .and_then(|v| v.as_f64())
-        .ok_or("'where.value' must be a number")?;
+    #[test]
+    fn count_total_by_and_distinct() {
+        let data = json!([
+            {"status": "active", "user": "a"},
+            {"status": "active", "user": "b"},
+            {"status": "inactive", "user": "a"},
+            {"status": "active", "user": "a"},
+            {"user": "c"}
+        ]);
+        let total = execute(&json!({"operation": "count", "data": data})).unwrap();
+        assert_eq!(total, json!({"count": 5}));
 
-    let filtered: Vec<&Value> = data
-        .iter()
-        .filter(|item| {
-            let val = item.get(field).and_then(|v| v.as_f64()).unwrap_or(0.0);
-            match op {
-                ">" => val > threshold,
-                ">=" => val >= threshold,
-                "<" => val < threshold,
-                "<=" => val <= threshold,
-                "==" => (val - threshold).abs() < f64::EPSILON,
-                "!=" => (val - threshold).abs() >= f64::EPSILON,
-                _ => false,
-            }
-        })
-        .collect();
+        let by = execute(&json!({"operation": "count", "data": data, "by": "status"})).unwrap();
+        assert_eq!(by["counts"], json!({"active": 3, "inactive": 1, "null": 1}));
 
-    Ok(json!({
-        "data": filtered,
-        "count": filtered.len(),
-        "original_count": data.len(),
-    }))
-}
+        let distinct = execute(&json!({
+            "operation": "count",
+            "data": data,
+            "distinct": "user",
+            "by": "status"
+        }))
+        .unwrap();
+        assert_eq!(distinct["count"], 3);
+        assert_eq!(distinct["total"], 5);
+        assert_eq!(
+            distinct["counts"],
+            json!({"active": 2, "inactive": 1, "null": 1})
+        );
+    }
 
-fn sort_data(input: &Value) -> Result<Value, String> {
-    let data = input
-        .get("data")
-        .and_then(|v| v.as_array())
-        .ok_or("'data' must be an array")?;
+    #[test]
+    fn correlate_pairs_numeric_rows() {
+        let result = execute(&json!({
+            "operation": "correlate",
+            "data": [
+                {"x": 1, "y": 2},
+                {"x": 2, "y": 4.1},
+                {"x": 3, "y": 5.9},
+                {"x": 4},
+                {"x": "5", "y": 10}
+            ],
+            "x": "x",
+            "y": "y"
+        }))
+        .unwrap();
+        assert_eq!(result["n"], 3);
+        assert!((result["r"].as_f64().unwrap() - 0.99902).abs() < 1e-4);
 
-    let field = input
-        .get("field")
-        .and_then(|v| v.as_str())
-        .ok_or("'field' is required for sort operation")?;
+        let flat = execute(&json!({
+            "operation": "correlate",
+            "data": [{"x": 1, "y": 3}, {"x": 2, "y": 3}],
+            "x": "x",
+            "y": "y"
+        }))
+        .unwrap();
+        assert_eq!(flat, json!({"r": null, "n": 2}));
+    }
 
-    let descending = input
-        .get("descending")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
+    #[test]
+    fn moving_average_trailing_window() {
+        let data = json!([{"v": 1}, {"v": 2}, {"v": 3}, {"v": "x"}, {"v": 7}]);
+        let result = execute(&json!({
+            "operation": "moving_average",
+            "data": data,
+            "field": "v",
+            "window": 3
+        }))
+        .unwrap();
+        assert_eq!(result["values"], json!([1.0, 1.5, 2.0, 2.5, 5.0]));
+        assert_eq!(result["data"][1], json!({"v": 2, "moving_average": 1.5}));
 
-    let mut sorted: Vec<Value> = data.clone();
-    sorted.sort_by(|a, b| {
-        let va = a.get(field).and_then(|v| v.as_f64()).unwrap_or(0.0);
-        let vb = b.get(field).and_then(|v| v.as_f64()).unwrap_or(0.0);
-        if descending {
-            vb.partial_cmp(&va).unwrap_or(std::cmp::Ordering::Equal)
-        } else {
-            va.partial_cmp(&vb).unwrap_or(std::cmp::Ordering::Equal)
-        }
-    });
+        let strict = execute(&json!({
+            "operation": "moving_average",
+            "data": data,
+            "field": "v",
+            "window": 3,
+            "partial": false,
+            "output": "ma3"
+        }))
+        .unwrap();
+        assert_eq!(strict["values"], json!([null, null, 2.0, 2.5, 5.0]));
+        assert_eq!(strict["data"][0], json!({"v": 1, "ma3": null}));
+    }
 
-    Ok(json!({"data": sorted, "count": sorted.len()}))
-}
+    #[test]
+    fn cumsum_carries_forward_and_resets_per_group() {
+        let data = json!([
+            {"c": "a", "amt": 5},
+            {"c": "a", "amt": null},
+            {"c": "a", "amt": 2},
+            {"c": "b", "amt": 4},
+            {"c": "b"}
+        ]);
+        let result =
+            execute(&json!({"operation": "cumsum", "data": data, "field": "amt"})).unwrap();
+        let totals: Vec<f64> = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["cumulative"].as_f64().unwrap())
+            .collect();
+        assert_eq!(totals, vec![5.0, 5.0, 7.0, 11.0, 11.0]);
 
-fn validate_schema(input: &Value) -> Result<Value, String> {
-    let data = input.get("data").ok_or("'data' is required for validate")?;
-    let schema = input
-        .get("schema")
-        .ok_or("'schema' is required for validate")?;
+        let result = execute(&json!({
+            "operation": "cumsum",
+            "data": data,
+            "field": "amt",
+            "reset_on": "c"
+        }))
+        .unwrap();
+        let totals: Vec<f64> = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["cumulative"].as_f64().unwrap())
+            .collect();
+        assert_eq!(totals, vec![5.0, 5.0, 7.0, 4.0, 4.0]);
+    }
 
-    let schema_type = schema
-        .get("type")
-        .and_then(|v| v.as_str())
-        .unwrap_or("any");
-    let mut errors: Vec<String> = Vec::new();
+    #[test]
+    fn normalize_minmax_zscore_and_flat_values() {
+        let data = json!([{"v": 10}, {"v": 20}, {"v": 30}, {"name": "no value"}]);
+        let minmax =
+            execute(&json!({"operation": "normalize", "data": data, "field": "v"})).unwrap();
+        assert_eq!(minmax["data"][1], json!({"v": 20, "normalized": 0.5}));
+        assert_eq!(minmax["data"][3], json!({"name": "no value"}));
+        assert_eq!(minmax["missing"], 1);
 
-    match schema_type {
-        "object" => {
-            if !data.is_object() {
-                errors.push(format!("Expected object, got {}", type_name(data)));
-            } else if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
-                for req in required {
-                    if let Some(field_name) = req.as_str() {
-                        if data.get(field_name).is_none() {
-                            errors.push(format!("Missing required field: {field_name}"));
-                        }
-                    }
-                }
-            }
-        }
-        "array" => {
-            if !data.is_array() {
-                errors.push(format!("Expected array, got {}", type_name(data)));
-            }
-        }
-        "string" => {
-            if !data.is_string() {
-                errors.push(format!("Expected string, got {}", type_name(data)));
-            }
-        }
-        "number" | "integer" => {
-            if !data.is_number() {
-                errors.push(format!("Expected number, got {}", type_name(data)));
-            }
-        }
-        _ => {}
+        let z = execute(&json!({
+            "operation": "normalize",
+            "data": data,
+            "field": "v",
+            "method": "zscore",
+            "output": "v_z"
+        }))
+        .unwrap();
+        assert_eq!(z["data"][1]["v_z"], 0.0);
+        assert!((z["data"][2]["v_z"].as_f64().unwrap() - 1.224_744_871).abs() < 1e-6);
+
+        let flat = execute(&json!({
+            "operation": "normalize",
+            "data": [{"v": 4}, {"v": 4}],
+            "field": "v"
+        }))
+        .unwrap();
+        assert_eq!(
+            flat["data"],
+            json!([{"v": 4, "normalized": 0.0}, {"v": 4, "normalized": 0.0}])
+        );
     }
 
-    Ok(json!({
-        "valid": errors.is_empty(),
-        "errors": errors,
-        "error_count": errors.len(),
-    }))
-}
+    #[test]
+    fn outliers_iqr_flags_and_zscore_removes() {
+        let data = json!([{"v": 1}, {"v": 2}, {"v": 3}, {"v": 4}, {"v": 100}, {"v": null}]);
+        let flagged =
+            execute(&json!({"operation": "outliers", "data": data, "field": "v"})).unwrap();
+        assert_eq!(flagged["outliers"], json!([{"v": 100}]));
+        assert_eq!(flagged["bounds"]["q1"], 2.0);
+        assert_eq!(flagged["bounds"]["q3"], 4.0);
+        assert_eq!(flagged["bounds"]["upper"], 7.0);
+        assert_eq!(flagged["data"][4], json!({"v": 100, "outlier": true}));
+        assert_eq!(flagged["data"][5], json!({"v": null}));
 
-fn aggregate_data(input: &Value) -> Result<Value, String> {
-    let data = input
-        .get("data")
-        .and_then(|v| v.as_array())
-        .ok_or("'data' must be an array")?;
+        let cleaned = execute(&json!({
+            "operation": "outliers",
+            "data": data,
+            "field": "v",
+            "method": "zscore",
+            "threshold": 1.5,
+            "action": "remove"
+        }))
+        .unwrap();
+        assert_eq!(cleaned["outlier_count"], 1);
+        assert_eq!(cleaned["count"], 5);
+    }
 
-    let compute = input
-        .get("compute")
-        .and_then(|v| v.as_array())
-        .ok_or("'compute' must be an array of operation names")?;
+    #[test]
+    fn top_n_selects_highest_or_lowest_with_stable_ties() {
+        let data = json!([
+            {"id": 1, "v": 5},
+            {"id": 2, "v": 9},
+            {"id": 3, "v": 7},
+            {"id": 4, "v": 9},
+            {"id": 5},
+            {"id": 6, "v": 1}
+        ]);
+        let ids = |result: Value| -> Vec<i64> {
+            result["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["id"].as_i64().unwrap())
+                .collect()
+        };
+        let top = execute(&json!({"operation": "top_n", "data": data, "field": "v", "n": 2}));
+        assert_eq!(ids(top.unwrap()), vec![2, 4]);
+        let bottom = execute(&json!({
+            "operation": "top_n",
+            "data": data,
+            "field": "v",
+            "n": 2,
+            "bottom": true
+        }));
+        assert_eq!(ids(bottom.unwrap()), vec![6, 1]);
+        let all = execute(&json!({"operation": "top_n", "data": data, "field": "v", "n": 50}));
+        assert_eq!(ids(all.unwrap()), vec![2, 4, 3, 1, 6]);
+    }
 
-    let field = input
-        .get("field")
-        .and_then(|v| v.as_str())
-        .unwrap_or("value");
+    #[test]
+    fn join_inner_left_and_outer() {
+        let orders = json!([
+            {"order": 1, "cust": "a", "total": 10},
+            {"order": 2, "cust": "b", "total": 20},
+            {"order": 3, "cust": "z", "total": 5}
+        ]);
+        let customers = json!([
+            {"id": "a", "name": "Ann", "total": 99},
+            {"id": "b", "name": "Bo"},
+            {"id": "c", "name": "Cy"}
+        ]);
+        let join = |kind: &str| {
+            execute(&json!({
+                "operation": "join",
+                "left": orders,
+                "right": customers,
+                "on": {"left": "cust", "right": "id"},
+                "type": kind
+            }))
+            .unwrap()
+        };
 
-    let values: Vec<f64> = data
-        .iter()
-        .filter_map(|item| {
-            // Handle plain numbers: [10, 20, 30]
-            if let Some(n) = item.as_f64() {
-                return Some(n);
-            }
-            // Handle objects with a field: [{"value": 10}, {"score": 20}]
-            item.get(field).and_then(|v| v.as_f64())
-        })
-        .collect();
+        let inner = join("inner");
+        assert_eq!(inner["count"], 2);
+        assert_eq!(
+            inner["data"][0],
+            json!({"order": 1, "cust": "a", "total": 10, "id": "a", "name": "Ann"})
+        );
+        assert_eq!(inner["conflicts"], json!(["total"]));
 
-    let mut result = serde_json::Map::new();
-    result.insert("count".into(), json!(values.len()));
+        let left = join("left");
+        assert_eq!(
+            left["data"][2],
+            json!({"order": 3, "cust": "z", "total": 5, "id": null, "name": null})
+        );
 
-    for op in compute {
-        if let Some(op_name) = op.as_str() {
-            match op_name {
-                "sum" => {
-                    result.insert("sum".into(), json!(values.iter().sum::<f64>()));
-                }
-                "mean" => {
-                    let mean = if values.is_empty() {
-                        0.0
-                    } else {
-                        values.iter().sum::<f64>() / values.len() as f64
-                    };
-                    result.insert("mean".into(), json!(mean));
-                }
-                "min" => {
-                    result.insert(
-                        "min".into(),
-                        json!(values.iter().cloned().fold(f64::INFINITY, f64::min)),
-                    );
-                }
-                "max" => {
-                    result.insert(
-                        "max".into(),
-                        json!(values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)),
-                    );
-                }
-                _ => {}
-            }
-        }
+        let outer = join("outer");
+        assert_eq!(outer["count"], 4);
+        assert_eq!(
+            outer["data"][3],
+            json!({"order": null, "cust": null, "total": null, "id": "c", "name": "Cy"})
+        );
     }
 
-    Ok(Value::Object(result))
-}
+    #[test]
+    fn pivot_long_to_wide() {
+        let data = json!([
+            {"region": "east", "q": "Q1", "sales": 10},
+            {"region": "east", "q": "Q2", "sales": 15},
+            {"region": "west", "q": "Q1", "sales": 7},
+            {"region": "east", "q": "Q1", "sales": 4}
+        ]);
+        let sum = execute(&json!({
+            "operation": "pivot",
+            "data": data,
+            "index": "region",
+            "columns": "q",
+            "values": "sales"
+        }))
+        .unwrap();
+        assert_eq!(
+            sum["data"],
+            json!([
+                {"region": "east", "Q1": 14.0, "Q2": 15.0},
+                {"region": "west", "Q1": 7.0, "Q2": null}
+            ])
+        );
+        assert_eq!(sum["columns"], json!(["Q1", "Q2"]));
 
-fn type_name(v: &Value) -> &str {
-    match v {
-        Value::Null => "null",
-        Value::Bool(_) => "boolean",
-        Value::Number(_) => "number",
-        Value::String(_) => "string",
-        Value::Array(_) => "array",
-        Value::Object(_) => "object",
+        let count = execute(&json!({
+            "operation": "pivot",
+            "data": data,
+            "index": "region",
+            "columns": "q",
+            "agg": "count",
+            "fill": 0
+        }))
+        .unwrap();
+        assert_eq!(
+            count["data"][1],
+            json!({"region": "west", "Q1": 1, "Q2": 0})
+        );
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
 
     #[test]
-    fn stats_basic() {
-        let result = execute(&json!({
-            "operation": "stats",
-            "data": [{"value": 10}, {"value": 20}, {"value": 30}],
-            "field": "value"
+    fn flatten_nested_objects_and_explode_arrays() {
+        let data = json!([
+            {"id": 1, "user": {"name": "ann", "geo": {"city": "Oslo"}}, "tags": ["a", "b"]},
+            {"id": 2, "user": {"name": "bo"}, "tags": []}
+        ]);
+        let flat = execute(&json!({"operation": "flatten", "data": data})).unwrap();
+        assert_eq!(
+            flat["data"][0],
+            json!({"id": 1, "user.name": "ann", "user.geo.city": "Oslo", "tags": ["a", "b"]})
+        );
+
+        let exploded = execute(&json!({
+            "operation": "flatten",
+            "data": data,
+            "explode": "tags",
+            "depth": 1
         }))
         .unwrap();
-        assert_eq!(result["count"], 3);
-        assert_eq!(result["sum"], 60.0);
-        assert_eq!(result["mean"], 20.0);
-        assert_eq!(result["min"], 10.0);
-        assert_eq!(result["max"], 30.0);
+        assert_eq!(exploded["count"], 3);
+        assert_eq!(
+            exploded["data"][1],
+            json!({"id": 1, "user.name": "ann", "user.geo": {"city": "Oslo"}, "tags": "b"})
+        );
+        assert_eq!(
+            exploded["data"][2],
+            json!({"id": 2, "user.name": "bo", "tags": null})
+        );
     }
 
     #[test]
-    fn filter_greater_than() {
+    fn fillna_literals_and_computed_fills() {
+        let data = json!([
+            {"name": "ann", "score": 4},
+            {"score": null},
+            {"name": null, "score": 8},
+            {"name": "cy"}
+        ]);
         let result = execute(&json!({
-            "operation": "filter",
-            "data": [{"x": 5}, {"x": 15}, {"x": 25}],
-            "where": {"field": "x", "op": ">", "value": 10}
+            "operation": "fillna",
+            "data": data,
+            "fill": {"name": "unknown", "score": "mean"}
         }))
         .unwrap();
-        assert_eq!(result["count"], 2);
+        assert_eq!(
+            result["data"],
+            json!([
+                {"name": "ann", "score": 4},
+                {"score": 6.0, "name": "unknown"},
+                {"name": "unknown", "score": 8},
+                {"name": "cy", "score": 6.0}
+            ])
+        );
+        assert_eq!(result["filled"], json!({"name": 2, "score": 2}));
+
+        let median = execute(&json!({
+            "operation": "fillna",
+            "data": [{"v": 1}, {"v": 2}, {"v": 10}, {}],
+            "fill": "median",
+            "fields": ["v"]
+        }))
+        .unwrap();
+        assert_eq!(median["data"][3], json!({"v": 2.0}));
     }
 
     #[test]
-    fn sort_ascending() {
-        let result = execute(&json!({
-            "operation": "sort",
-            "data": [{"v": 30}, {"v": 10}, {"v": 20}],
-            "field": "v"
+    fn map_computes_derived_fields() {
+        let data = json!([
+            {"price": 2.5, "qty": 4},
+            {"price": 3, "qty": 0},
+            {"price": 1}
+        ]);
+        let rules = json!([
+            {"target": "total", "expr": {"op": "*", "args": ["price", {"field": "qty"}]}},
+            {"target": "unit", "expr": {"op": "/", "args": ["total", "qty"]}},
+            {"target": "net", "expr": {"op": "-", "args": ["total", {"value": 1}]}}
+        ]);
+        let result = execute(&json!({"operation": "map", "data": data, "rules": rules})).unwrap();
+        assert_eq!(
+            result["data"],
+            json!([
+                {"price": 2.5, "qty": 4, "total": 10.0, "unit": 2.5, "net": 9.0},
+                {"price": 3, "qty": 0, "total": 0.0, "unit": null, "net": -1.0},
+                {"price": 1}
+            ])
+        );
+        assert_eq!(result["skipped"], 3);
+
+        let nulls = execute(&json!({
+            "operation": "map",
+            "data": [{"price": 1}],
+            "rules": [{"target": "total", "expr": {"op": "*", "args": ["price", "qty"]}}],
+            "on_missing": "null"
         }))
         .unwrap();
-        let sorted = result["data"].as_array().unwrap();
-        assert_eq!(sorted[0]["v"], 10);
-        assert_eq!(sorted[2]["v"], 30);
+        assert_eq!(nulls["data"][0], json!({"price": 1, "total": null}));
+
+        let err = execute(&json!({
+            "operation": "map",
+            "data": [],
+            "rules": [{"target": "t", "expr": {"op": "^", "args": [1, 2]}}]
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'rules[0].expr': Unknown op: ^. Use: +, -, *, /"
+        );
     }
 
     #[test]
-    fn validate_required_field_missing() {
+    fn csv_input_feeds_any_operation() {
         let result = execute(&json!({
-            "operation": "validate",
-            "data": {"name": "test"},
-            "schema": {"type": "object", "required": ["name", "version"]}
+            "operation": "filter",
+            "format": "csv",
+            "csv": "name;note;score\nann;\"likes; commas\nand lines\";12\nbo;plain;3\n",
+            "delimiter": ";",
+            "where": {"field": "score", "op": ">", "value": 5}
         }))
         .unwrap();
-        assert_eq!(result["valid"], false);
-        assert_eq!(result["error_count"], 1);
+        assert_eq!(
+            result["data"],
+            json!([{"name": "ann", "note": "likes; commas\nand lines", "score": 12}])
+        );
+
+        let err = execute(&json!({"operation": "stats", "format": "csv"})).unwrap_err();
+        assert!(matches!(err, TransformError::MissingField(_)));
     }
 
     #[test]
-    fn validate_passes() {
+    fn csv_output_serializes_result_data() {
         let result = execute(&json!({
-            "operation": "validate",
-            "data": {"name": "test", "version": 3},
-            "schema": {"type": "object", "required": ["name", "version"]}
+            "operation": "sort",
+            "data": [
+                {"name": "Smith, J", "age": 40},
+                {"name": "say \"hi\"", "city": "Oslo", "age": 30}
+            ],
+            "field": "age",
+            "output_format": "csv"
         }))
         .unwrap();
-        assert_eq!(result["valid"], true);
+        assert_eq!(
+            result["data"],
+            "name,city,age\n\"say \"\"hi\"\"\",Oslo,30\n\"Smith, J\",,40\n"
+        );
+
+        // `output` still names the written field alongside CSV output
+        let scored = execute(&json!({
+            "operation": "zscore",
+            "data": [{"v": 1}, {"v": 3}],
+            "field": "v",
+            "output": "csv",
+            "output_format": "csv"
+        }))
+        .unwrap();
+        assert_eq!(scored["data"], "v,csv\n1,-1.0\n3,1.0\n");
     }
 
     #[test]
-    fn aggregate_sum_and_mean() {
+    fn pipeline_chains_steps_and_traces_counts() {
         let result = execute(&json!({
-            "operation": "aggregate",
-            "data": [{"v": 10}, {"v": 20}, {"v": 30}],
-            "field": "v",
-            "compute": ["sum", "mean", "min", "max"]
+            "operation": "pipeline",
+            "data": [
+                {"name": "a", "v": 3, "x": 1},
+                {"name": "b", "v": 9, "x": 2},
+                {"name": "c", "v": 6, "x": 3}
+            ],
+            "steps": [
+                {"operation": "filter", "where": {"field": "v", "op": ">", "value": 4}},
+                {"operation": "sort", "field": "v", "descending": true},
+                {"operation": "select", "fields": ["name"]}
+            ],
+            "trace": true
         }))
         .unwrap();
-        assert_eq!(result["sum"], 60.0);
-        assert_eq!(result["mean"], 20.0);
+        assert_eq!(result["data"], json!([{"name": "b"}, {"name": "c"}]));
+        assert_eq!(
+            result["trace"],
+            json!([
+                {"operation": "filter", "count": 2},
+                {"operation": "sort", "count": 2},
+                {"operation": "select", "count": 2}
+            ])
+        );
+
+        let err = execute(&json!({
+            "operation": "pipeline",
+            "data": [],
+            "steps": [{"operation": "filter", "where": {"field": "v", "op": ">", "value": 1}}, {"operation": "sort"}]
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err,
+            TransformError::MissingField(
+                "Step 1 (sort): 'field' is required for sort operation".into()
+            )
+        );
     }
 
     #[test]
@@ -374,4 +8285,45 @@ mod tests {
         assert_eq!(result["max"], 50.0);
     }
 
+    #[test]
+    fn nearest_returns_closest_records_with_distance() {
+        let result = execute(&json!({
+            "operation": "nearest",
+            "data": [
+                {"id": "a", "x": 0, "y": 0},
+                {"id": "b", "x": 10, "y": 10},
+                {"id": "c", "x": 1, "y": 1},
+                {"id": "d", "y": 1}
+            ],
+            "target": {"x": 0, "y": 1},
+            "fields": ["x", "y"],
+            "n": 2
+        }))
+        .unwrap();
+        let nearest = result["data"].as_array().unwrap();
+        assert_eq!(result["count"], 2);
+        assert_eq!(result["candidates"], 3);
+        assert_eq!(nearest[0]["id"], "a");
+        assert_eq!(nearest[0]["distance"], 1.0);
+        assert_eq!(nearest[1]["id"], "c");
+    }
+
+    #[test]
+    fn nearest_normalizes_fields_on_different_scales() {
+        let result = execute(&json!({
+            "operation": "nearest",
+            "data": [
+                {"id": "a", "age": 30, "income": 90000},
+                {"id": "b", "age": 60, "income": 50500},
+                {"id": "c", "age": 31, "income": 10000}
+            ],
+            "target": {"age": 30, "income": 50000},
+            "fields": ["age", "income"],
+            "n": 1,
+            "normalize": true
+        }))
+        .unwrap();
+        // Unscaled, income dominates and "b" wins; scaled, age matters too
+        assert_eq!(result["data"][0]["id"], "a");
+    }
 }